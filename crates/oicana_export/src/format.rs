@@ -0,0 +1,162 @@
+use std::io::Write;
+
+use oicana_world::diagnostics::TemplateDiagnostics;
+use typst::layout::PagedDocument;
+
+#[cfg(any(feature = "png", feature = "svg", feature = "raster"))]
+use crate::pages::ImageExportOptions;
+#[cfg(feature = "pdf")]
+use crate::pdf::PdfExportOptions;
+#[cfg(feature = "raster")]
+use crate::raster::{RasterFormat, ThumbnailOptions};
+
+/// A format to export a compiled document into.
+#[derive(Debug, Clone)]
+pub enum ExportFormat {
+    /// A single PDF file.
+    #[cfg(feature = "pdf")]
+    Pdf(PdfExportOptions),
+    /// The selected pages merged into a single PNG image.
+    #[cfg(feature = "png")]
+    Png(ImageExportOptions),
+    /// The selected pages merged into a single raster image.
+    #[cfg(feature = "raster")]
+    Raster(ImageExportOptions, RasterFormat),
+    /// A thumbnail of the first page.
+    #[cfg(feature = "raster")]
+    Thumbnail(ThumbnailOptions),
+    /// The selected pages merged into a single SVG image.
+    #[cfg(feature = "svg")]
+    Svg(ImageExportOptions),
+    /// The plain text of all pages, separated by form feeds.
+    #[cfg(feature = "text")]
+    Text,
+}
+
+/// Export the document into the given format.
+#[cfg_attr(
+    not(feature = "pdf"),
+    allow(unused_variables, reason = "diagnostics are only needed for PDF")
+)]
+pub fn export<Diagnostics: TemplateDiagnostics>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    format: &ExportFormat,
+) -> Result<Vec<u8>, String> {
+    match format {
+        #[cfg(feature = "pdf")]
+        ExportFormat::Pdf(options) => crate::pdf::export_merged_pdf(document, diagnostics, options),
+        #[cfg(feature = "png")]
+        ExportFormat::Png(options) => {
+            crate::png::export_png(document, options).map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "raster")]
+        ExportFormat::Raster(options, raster_format) => {
+            crate::raster::export_raster(document, options, *raster_format)
+                .map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "raster")]
+        ExportFormat::Thumbnail(options) => crate::raster::export_thumbnail(document, options)
+            .map_err(|error| format!("Failed to create thumbnail: {error}")),
+        #[cfg(feature = "svg")]
+        ExportFormat::Svg(options) => {
+            crate::svg::export_svg(document, options).map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "text")]
+        ExportFormat::Text => Ok(crate::text::export_text(document).into_bytes()),
+    }
+}
+
+/// Export the document into the given format and write the result into the writer.
+///
+/// Unlike [`export`], most formats are encoded directly into the writer without keeping
+/// another copy of the output in memory.
+#[cfg_attr(
+    not(feature = "pdf"),
+    allow(unused_variables, reason = "diagnostics are only needed for PDF")
+)]
+pub fn write<Diagnostics: TemplateDiagnostics, Writer: Write>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    format: &ExportFormat,
+    writer: &mut Writer,
+) -> Result<(), String> {
+    match format {
+        #[cfg(feature = "pdf")]
+        ExportFormat::Pdf(options) => {
+            crate::pdf::write_merged_pdf(document, diagnostics, options, writer)
+        }
+        #[cfg(feature = "png")]
+        ExportFormat::Png(options) => {
+            crate::png::write_png(document, options, writer).map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "raster")]
+        ExportFormat::Raster(options, raster_format) => {
+            crate::raster::write_raster(document, options, *raster_format, writer)
+                .map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "raster")]
+        ExportFormat::Thumbnail(_) => {
+            // Thumbnails are small, so they are encoded into memory first.
+            let thumbnail = export(document, diagnostics, format)?;
+            writer
+                .write_all(&thumbnail)
+                .map_err(|error| format!("Failed to write thumbnail: {error}"))
+        }
+        #[cfg(feature = "svg")]
+        ExportFormat::Svg(options) => {
+            crate::svg::write_svg(document, options, writer).map_err(|error| error.to_string())
+        }
+        #[cfg(feature = "text")]
+        ExportFormat::Text => crate::text::write_text(document, writer)
+            .map_err(|error| format!("Failed to write text: {error}")),
+    }
+}
+
+/// Export the document into all given formats.
+///
+/// The outputs are returned in the order of the formats.
+/// Fails with the first error of any format.
+pub fn export_all<Diagnostics: TemplateDiagnostics>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    formats: &[ExportFormat],
+) -> Result<Vec<Vec<u8>>, String> {
+    formats
+        .iter()
+        .map(|format| export(document, diagnostics, format))
+        .collect()
+}
+
+#[cfg(all(test, feature = "pdf", feature = "png"))]
+mod tests {
+    use crate::format::{export_all, ExportFormat};
+    use crate::pages::ImageExportOptions;
+    use crate::pdf::PdfExportOptions;
+    use crate::test_world;
+    use std::num::NonZeroUsize;
+    use typst::layout::PageRanges;
+
+    #[test]
+    fn exports_pdf_and_png_of_first_page() {
+        let mut world = test_world("#set page(width: 100pt, height: 50pt)\nOne\n#pagebreak()\nTwo");
+        let document = world.compile().expect("Failed to compile").document;
+        let first_page = NonZeroUsize::new(1);
+        let formats = [
+            ExportFormat::Pdf(PdfExportOptions::default()),
+            ExportFormat::Png(ImageExportOptions {
+                pages: Some(PageRanges::new(vec![first_page..=first_page])),
+                ..ImageExportOptions::default()
+            }),
+        ];
+
+        let outputs = export_all(&document, &world, &formats).expect("Failed to export");
+
+        assert_eq!(outputs.len(), 2);
+        assert!(outputs[0].starts_with(b"%PDF"));
+        let png = png::Decoder::new(outputs[1].as_slice())
+            .read_info()
+            .expect("Failed to decode PNG");
+        assert_eq!((png.info().width, png.info().height), (100, 50));
+    }
+}
@@ -1,6 +1,52 @@
+#[cfg(any(
+    feature = "pdf",
+    feature = "png",
+    feature = "svg",
+    feature = "raster",
+    feature = "text"
+))]
+pub mod format;
+#[cfg(any(feature = "png", feature = "svg", feature = "raster"))]
+pub mod pages;
 #[cfg(feature = "pdf")]
 pub mod pdf;
 #[cfg(feature = "png")]
 pub mod png;
+#[cfg(feature = "raster")]
+pub mod raster;
 #[cfg(feature = "svg")]
 pub mod svg;
+#[cfg(feature = "text")]
+pub mod text;
+
+/// A world for a template with the given main file.
+#[cfg(test)]
+pub(crate) fn test_world(
+    main: &str,
+) -> oicana_world::world::OicanaWorld<oicana_files::preloaded::PreloadedTemplate> {
+    use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_input::TemplateInputs;
+    use oicana_world::manifest::OicanaWorldFiles;
+    use oicana_world::world::OicanaWorld;
+    use std::collections::HashMap;
+
+    let mut files = HashMap::new();
+    files.insert("main.typ".to_owned(), main.to_owned());
+    files.insert(
+        "typst.toml".to_owned(),
+        r#"
+        [package]
+        entrypoint = "main.typ"
+        name = "test"
+        version = "0.1.0"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#
+        .to_owned(),
+    );
+    let files = PreloadedTemplate::new(files);
+    let manifest = files.manifest().expect("should be able to parse manifest");
+
+    OicanaWorld::new(files, TemplateInputs::new(), manifest).expect("Failed to create world")
+}
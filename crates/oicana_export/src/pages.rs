@@ -0,0 +1,116 @@
+use std::num::NonZeroUsize;
+
+use thiserror::Error;
+use typst::foundations::Smart;
+use typst::layout::{Abs, Page, PageRanges, PagedDocument};
+use typst::visualize::{Color, Paint};
+
+/// Options for exporting the pages of a document as images.
+#[derive(Debug, Clone)]
+pub struct ImageExportOptions {
+    /// The pages to export. All pages are exported if this is `None`.
+    pub pages: Option<PageRanges>,
+    /// Space between pages when merging them into a single image.
+    pub gap: Abs,
+    /// Fill of pages without an explicit fill and of the gaps between merged pages.
+    ///
+    /// If this is `None`, such pages are white and gaps are transparent.
+    /// SVG exports only apply the background to pages.
+    pub background: Option<Color>,
+    /// Resolution of raster images in pixels per pt. Ignored for SVG exports.
+    pub pixels_per_pt: f32,
+}
+
+impl Default for ImageExportOptions {
+    fn default() -> Self {
+        ImageExportOptions {
+            pages: None,
+            gap: Abs::pt(15.),
+            background: None,
+            pixels_per_pt: 1.0,
+        }
+    }
+}
+
+impl ImageExportOptions {
+    /// Pages of the document selected by these options, together with their one-indexed page number.
+    ///
+    /// The configured background is already applied to the returned pages.
+    /// Fails if the selection does not contain any page of the document, for example because a
+    /// range starts after the last page or ends before it starts.
+    pub fn selected_pages(
+        &self,
+        document: &PagedDocument,
+    ) -> Result<Vec<(NonZeroUsize, Page)>, NoPagesSelected> {
+        let pages: Vec<_> = document
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                self.pages
+                    .as_ref()
+                    .is_none_or(|pages| pages.includes_page_index(*index))
+            })
+            .map(|(index, page)| {
+                let mut page = page.clone();
+                if let (Smart::Auto, Some(background)) = (&page.fill, self.background) {
+                    page.fill = Smart::Custom(Some(Paint::Solid(background)));
+                }
+                (
+                    NonZeroUsize::new(index + 1).expect("index + 1 is never zero"),
+                    page,
+                )
+            })
+            .collect();
+        if pages.is_empty() {
+            return Err(NoPagesSelected {
+                page_count: document.pages.len(),
+            });
+        }
+
+        Ok(pages)
+    }
+
+    /// A document only containing the selected pages.
+    ///
+    /// Merged exports only look at the pages, so metadata and introspection are left empty.
+    pub(crate) fn selected_document(
+        &self,
+        document: &PagedDocument,
+    ) -> Result<PagedDocument, NoPagesSelected> {
+        Ok(PagedDocument {
+            pages: self
+                .selected_pages(document)?
+                .into_iter()
+                .map(|(_, page)| page)
+                .collect(),
+            ..PagedDocument::default()
+        })
+    }
+}
+
+/// The page selection of an image export does not contain any page of the document.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+#[error("No pages selected for export, the document has {page_count} pages")]
+pub struct NoPagesSelected {
+    /// Number of pages in the document.
+    pub page_count: usize,
+}
+
+/// Convert premultiplied RGBA pixel data to straight alpha, as expected by image encoders.
+#[cfg(any(feature = "png", feature = "raster"))]
+pub(crate) fn demultiply(pixels: &[u8]) -> Vec<u8> {
+    pixels
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = u16::from(pixel[3]);
+            let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|channel| match alpha {
+                0 => 0,
+                255 => channel,
+                _ => ((u16::from(channel) * 255 + alpha / 2) / alpha) as u8,
+            });
+
+            [r, g, b, pixel[3]]
+        })
+        .collect()
+}
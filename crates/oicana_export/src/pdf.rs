@@ -1,20 +1,190 @@
+use std::io::Write;
+
 use oicana_world::diagnostics::TemplateDiagnostics;
-use typst::{foundations::Smart, layout::PagedDocument};
+use typst::{
+    foundations::Smart,
+    layout::{PageRanges, PagedDocument},
+};
 use typst_pdf::{PdfOptions, PdfStandard, PdfStandards};
 
+pub use concatenate::{export_concatenated_pdf, ConcatenatedPdfOptions, PdfPart};
+pub use encryption::{PdfEncryption, PdfPermissions};
+pub use split::{export_split_pdf, SplitPdf};
+
+mod concatenate;
+mod encryption;
+mod split;
+
+/// Options for exporting a document as PDF.
+#[derive(Debug, Clone, Default)]
+pub struct PdfExportOptions {
+    /// Password protect the exported PDF.
+    ///
+    /// PDF/A does not allow encryption. Encrypted documents are exported without
+    /// claiming conformance to PDF/A-3b.
+    pub encryption: Option<PdfEncryption>,
+}
+
+impl PdfExportOptions {
+    fn standards(&self) -> PdfStandards {
+        if self.encryption.is_some() {
+            PdfStandards::default()
+        } else {
+            PdfStandards::new(&[PdfStandard::A_3b]).expect("Invalid combination of PDF standards")
+        }
+    }
+
+    fn apply_encryption(&self, pdf: Vec<u8>) -> Result<Vec<u8>, String> {
+        match self.encryption {
+            Some(ref encryption) => encryption
+                .encrypt(&pdf)
+                .map_err(|error| format!("Failed to encrypt PDF: {error}")),
+            None => Ok(pdf),
+        }
+    }
+
+    fn write_with_encryption<Writer: Write>(
+        &self,
+        pdf: &[u8],
+        writer: &mut Writer,
+    ) -> Result<(), String> {
+        match self.encryption {
+            Some(ref encryption) => encryption
+                .encrypt_into(pdf, writer)
+                .map_err(|error| format!("Failed to encrypt PDF: {error}")),
+            None => writer
+                .write_all(pdf)
+                .map_err(|error| format!("Failed to write PDF: {error}")),
+        }
+    }
+}
+
 pub fn export_merged_pdf<Diagnostics: TemplateDiagnostics>(
     document: &PagedDocument,
     diagnostics: &Diagnostics,
+    options: &PdfExportOptions,
 ) -> Result<Vec<u8>, String> {
-    let options = PdfOptions {
+    let pdf = export_pdf_with_standards(document, diagnostics, options.standards(), None)?;
+
+    options.apply_encryption(pdf)
+}
+
+/// Export the document as PDF into the given writer.
+///
+/// The PDF is still encoded into memory as a whole before anything is written. With encryption,
+/// it is also parsed and encrypted in memory. Compared to [`export_merged_pdf`], this only saves
+/// the returned copy of the finished PDF.
+pub fn write_merged_pdf<Diagnostics: TemplateDiagnostics, Writer: Write>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    options: &PdfExportOptions,
+    writer: &mut Writer,
+) -> Result<(), String> {
+    let pdf = export_pdf_with_standards(document, diagnostics, options.standards(), None)?;
+
+    options.write_with_encryption(&pdf, writer)
+}
+
+fn export_pdf_with_standards<Diagnostics: TemplateDiagnostics + ?Sized>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    standards: PdfStandards,
+    page_ranges: Option<PageRanges>,
+) -> Result<Vec<u8>, String> {
+    let pdf_options = PdfOptions {
         ident: Smart::Auto,
         timestamp: None,
-        page_ranges: None,
-        standards: PdfStandards::new(&[PdfStandard::A_3b])
-            .expect("Invalid combination of PDF standards"),
+        page_ranges,
+        standards,
     };
 
-    typst_pdf::pdf(document, &options).map_err(|source_error| {
-        String::from_utf8_lossy(&diagnostics.format_diagnostics(source_error)).into()
+    typst_pdf::pdf(document, &pdf_options).map_err(|source_error| {
+        String::from_utf8_lossy(&diagnostics.format_diagnostics(source_error)).to_string()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::{
+        export_merged_pdf, write_merged_pdf, PdfEncryption, PdfExportOptions, PdfPermissions,
+    };
+    use crate::test_world;
+    use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_world::world::OicanaWorld;
+
+    fn world() -> OicanaWorld<PreloadedTemplate> {
+        test_world("Hello PDF")
+    }
+
+    #[test]
+    fn rejects_empty_owner_password() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = PdfExportOptions {
+            encryption: Some(PdfEncryption {
+                user_password: "user".to_owned(),
+                owner_password: String::new(),
+                permissions: PdfPermissions::default(),
+            }),
+        };
+
+        assert!(export_merged_pdf(&document, &world, &options).is_err());
+    }
+
+    #[test]
+    fn encrypts_pdf_with_user_password() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = PdfExportOptions {
+            encryption: Some(PdfEncryption {
+                user_password: "user".to_owned(),
+                owner_password: "owner".to_owned(),
+                permissions: PdfPermissions {
+                    print: true,
+                    copy: false,
+                    modify: false,
+                },
+            }),
+        };
+
+        let pdf = export_merged_pdf(&document, &world, &options).expect("Failed to export PDF");
+
+        let encrypted = lopdf::Document::load_mem(&pdf).expect("Failed to parse PDF");
+        assert!(encrypted.is_encrypted());
+        assert!(encrypted.authenticate_user_password("wrong").is_err());
+        assert!(encrypted.authenticate_user_password("user").is_ok());
+        assert!(encrypted.authenticate_owner_password("owner").is_ok());
+    }
+
+    #[test]
+    fn does_not_encrypt_by_default() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+
+        let pdf = export_merged_pdf(&document, &world, &PdfExportOptions::default())
+            .expect("Failed to export PDF");
+
+        let document = lopdf::Document::load_mem(&pdf).expect("Failed to parse PDF");
+        assert!(!document.is_encrypted());
+    }
+
+    #[test]
+    fn writes_encrypted_pdf_into_writer() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = PdfExportOptions {
+            encryption: Some(PdfEncryption {
+                user_password: "user".to_owned(),
+                owner_password: "owner".to_owned(),
+                permissions: PdfPermissions::default(),
+            }),
+        };
+
+        let mut pdf = Vec::new();
+        write_merged_pdf(&document, &world, &options, &mut pdf).expect("Failed to write PDF");
+
+        let encrypted = lopdf::Document::load_mem(&pdf).expect("Failed to parse PDF");
+        assert!(encrypted.is_encrypted());
+        assert!(encrypted.authenticate_user_password("user").is_ok());
+    }
+}
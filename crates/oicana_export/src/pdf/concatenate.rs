@@ -0,0 +1,219 @@
+use lopdf::{dictionary, Bookmark, Dictionary, Document, Object, ObjectId};
+use oicana_world::diagnostics::TemplateDiagnostics;
+use typst::layout::PagedDocument;
+use typst_pdf::PdfStandards;
+
+use crate::pdf::{export_pdf_with_standards, PdfEncryption};
+
+/// Page attributes that a page can inherit from its ancestors in the page tree.
+const INHERITABLE_PAGE_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// A compiled document that is part of a concatenated PDF.
+pub struct PdfPart<'a> {
+    /// The compiled document.
+    pub document: &'a PagedDocument,
+    /// Formats errors of the PDF export. Usually the World the document was compiled with.
+    pub diagnostics: &'a dyn TemplateDiagnostics,
+    /// Title of the outline entry for this document.
+    ///
+    /// Falls back to the title of the document and then to its position, like "Document 3".
+    pub title: Option<String>,
+}
+
+/// Options for concatenating documents into a single PDF.
+#[derive(Debug, Clone, Default)]
+pub struct ConcatenatedPdfOptions {
+    /// Add a blank page after every document with an odd number of pages.
+    ///
+    /// In duplex printing, every document then starts on the front of a new sheet.
+    pub duplex_padding: bool,
+    /// Password protect the exported PDF.
+    pub encryption: Option<PdfEncryption>,
+}
+
+/// Concatenate multiple compiled documents into a single PDF.
+///
+/// The documents can be compiled from different templates. Every document gets one entry
+/// in the outline of the PDF, pointing at its first page. Documents without pages are skipped.
+///
+/// The concatenated PDF does not claim conformance to PDF/A, and the outlines
+/// of the individual documents are not included.
+pub fn export_concatenated_pdf(
+    parts: &[PdfPart<'_>],
+    options: &ConcatenatedPdfOptions,
+) -> Result<Vec<u8>, String> {
+    let mut merged = Document::with_version("1.7");
+    let pages_id = merged.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+
+    for (index, part) in parts.iter().enumerate() {
+        let pdf = export_pdf_with_standards(
+            part.document,
+            part.diagnostics,
+            PdfStandards::default(),
+            None,
+        )?;
+        let mut document = Document::load_mem(&pdf)
+            .map_err(|error| format!("Failed to read PDF of document {}: {error}", index + 1))?;
+        document.renumber_objects_with(merged.max_id + 1);
+
+        let page_ids: Vec<ObjectId> = document.get_pages().into_values().collect();
+        let (Some(&first_page), Some(&last_page)) = (page_ids.first(), page_ids.last()) else {
+            continue;
+        };
+        for &page_id in &page_ids {
+            let page = detach_page(&document, page_id, pages_id)
+                .map_err(|error| format!("Invalid page in document {}: {error}", index + 1))?;
+            document.objects.insert(page_id, page.into());
+            kids.push(page_id.into());
+        }
+
+        // The catalog, page tree and outline of the document are no longer referenced and get pruned.
+        merged.max_id = merged.max_id.max(document.max_id);
+        merged.objects.extend(document.objects);
+
+        let title = part
+            .title
+            .clone()
+            .or_else(|| {
+                part.document
+                    .info
+                    .title
+                    .as_ref()
+                    .map(|title| title.to_string())
+            })
+            .unwrap_or_else(|| format!("Document {}", index + 1));
+        merged.add_bookmark(Bookmark::new(title, [0.0, 0.0, 0.0], 0, first_page), None);
+
+        if options.duplex_padding && page_ids.len() % 2 == 1 {
+            let media_box = merged
+                .get_dictionary(last_page)
+                .and_then(|page| page.get(b"MediaBox"))
+                .map_err(|error| format!("Invalid page in document {}: {error}", index + 1))?
+                .clone();
+            let blank_page = merged.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => media_box,
+                "Resources" => dictionary! {},
+            });
+            kids.push(blank_page.into());
+        }
+    }
+
+    let count = kids.len() as i64;
+    merged.objects.insert(
+        pages_id,
+        dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }
+        .into(),
+    );
+    let mut catalog = dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    };
+    if let Some(outline_id) = merged.build_outline() {
+        catalog.set("Outlines", outline_id);
+        catalog.set("PageMode", "UseOutlines");
+    }
+    let catalog_id = merged.add_object(catalog);
+    merged.trailer.set("Root", catalog_id);
+    merged.prune_objects();
+
+    if let Some(ref encryption) = options.encryption {
+        encryption
+            .encrypt_document(&mut merged)
+            .map_err(|error| format!("Failed to encrypt PDF: {error}"))?;
+    }
+
+    let mut buffer = Vec::new();
+    merged
+        .save_to(&mut buffer)
+        .map_err(|error| format!("Failed to write PDF: {error}"))?;
+
+    Ok(buffer)
+}
+
+/// Copy the page with all inherited attributes and attach it to the given page tree node.
+fn detach_page(
+    document: &Document,
+    page_id: ObjectId,
+    parent: ObjectId,
+) -> Result<Dictionary, lopdf::Error> {
+    let mut page = document.get_dictionary(page_id)?.clone();
+    for key in INHERITABLE_PAGE_ATTRIBUTES {
+        if page.has(key) {
+            continue;
+        }
+        let mut node = page.get(b"Parent").and_then(Object::as_reference).ok();
+        while let Some(node_id) = node {
+            let ancestor = document.get_dictionary(node_id)?;
+            if let Ok(value) = ancestor.get(key) {
+                page.set(key, value.clone());
+                break;
+            }
+            node = ancestor.get(b"Parent").and_then(Object::as_reference).ok();
+        }
+    }
+    page.set("Parent", parent);
+
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::{export_concatenated_pdf, ConcatenatedPdfOptions, PdfPart};
+    use crate::test_world;
+
+    #[test]
+    fn concatenates_documents_with_padding_and_outline() {
+        let mut letter = test_world("#set document(title: \"Letter\")\nDear reader");
+        let mut report = test_world("One\n#pagebreak()\nTwo");
+        let letter_document = letter.compile().expect("Failed to compile").document;
+        let report_document = report.compile().expect("Failed to compile").document;
+        let parts = [
+            PdfPart {
+                document: &letter_document,
+                diagnostics: &letter,
+                title: None,
+            },
+            PdfPart {
+                document: &report_document,
+                diagnostics: &report,
+                title: Some("Report".to_owned()),
+            },
+            PdfPart {
+                document: &letter_document,
+                diagnostics: &letter,
+                title: Some("Second letter".to_owned()),
+            },
+        ];
+        let options = ConcatenatedPdfOptions {
+            duplex_padding: true,
+            ..ConcatenatedPdfOptions::default()
+        };
+
+        let pdf = export_concatenated_pdf(&parts, &options).expect("Failed to export PDF");
+
+        let document = lopdf::Document::load_mem(&pdf).expect("Failed to parse PDF");
+        assert_eq!(document.get_pages().len(), 6);
+        let outline: Vec<(String, usize)> = document
+            .get_toc()
+            .expect("PDF should have an outline")
+            .toc
+            .into_iter()
+            .map(|entry| (entry.title, entry.page))
+            .collect();
+        assert_eq!(
+            outline,
+            [
+                ("Letter".to_owned(), 1),
+                ("Report".to_owned(), 3),
+                ("Second letter".to_owned(), 5)
+            ]
+        );
+    }
+}
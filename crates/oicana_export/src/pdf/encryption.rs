@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Arc;
+
+use lopdf::encryption::crypt_filters::{Aes256CryptFilter, CryptFilter};
+use lopdf::{Document, EncryptionState, EncryptionVersion, Permissions};
+
+/// Password protection for an exported PDF.
+///
+/// The document is encrypted with AES-256 (PDF 2.0 standard security handler, revision 6).
+#[derive(Debug, Clone)]
+pub struct PdfEncryption {
+    /// Password required to open the document.
+    ///
+    /// An empty user password allows everyone to open the document,
+    /// but the permissions are still enforced by compliant PDF readers.
+    pub user_password: String,
+    /// Password that grants full access to the document, ignoring [`Self::permissions`].
+    ///
+    /// Must not be empty. Readers would otherwise grant full access with the empty password.
+    pub owner_password: String,
+    /// What users opening the document with the user password are allowed to do.
+    pub permissions: PdfPermissions,
+}
+
+/// Permissions granted to users opening an encrypted PDF with the user password.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdfPermissions {
+    /// Allow printing the document in full quality.
+    pub print: bool,
+    /// Allow copying or otherwise extracting text and graphics.
+    pub copy: bool,
+    /// Allow modifying the document, including annotations, form fields and page assembly.
+    pub modify: bool,
+}
+
+impl Default for PdfPermissions {
+    fn default() -> Self {
+        PdfPermissions {
+            print: true,
+            copy: true,
+            modify: true,
+        }
+    }
+}
+
+impl From<PdfPermissions> for Permissions {
+    fn from(value: PdfPermissions) -> Self {
+        // Assistive technology should always be able to read the document.
+        let mut permissions = Permissions::COPYABLE_FOR_ACCESSIBILITY;
+        if value.print {
+            permissions |= Permissions::PRINTABLE | Permissions::PRINTABLE_IN_HIGH_QUALITY;
+        }
+        if value.copy {
+            permissions |= Permissions::COPYABLE;
+        }
+        if value.modify {
+            permissions |= Permissions::MODIFIABLE
+                | Permissions::ANNOTABLE
+                | Permissions::FILLABLE
+                | Permissions::ASSEMBLABLE;
+        }
+
+        permissions
+    }
+}
+
+impl PdfEncryption {
+    /// Encrypt the given PDF file.
+    pub(crate) fn encrypt(&self, pdf: &[u8]) -> Result<Vec<u8>, lopdf::Error> {
+        let mut buffer = Vec::with_capacity(pdf.len());
+        self.encrypt_into(pdf, &mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    /// Encrypt the given PDF file and write the result into the writer.
+    pub(crate) fn encrypt_into<Writer: Write>(
+        &self,
+        pdf: &[u8],
+        writer: &mut Writer,
+    ) -> Result<(), lopdf::Error> {
+        let mut document = Document::load_mem(pdf)?;
+        self.encrypt_document(&mut document)?;
+
+        document.save_to(writer)?;
+
+        Ok(())
+    }
+
+    /// Encrypt the given parsed PDF document in place.
+    pub(crate) fn encrypt_document(&self, document: &mut Document) -> Result<(), lopdf::Error> {
+        if self.owner_password.is_empty() {
+            return Err(lopdf::Error::IO(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the owner password must not be empty",
+            )));
+        }
+
+        let mut file_encryption_key = [0u8; 32];
+        getrandom::getrandom(&mut file_encryption_key)
+            .map_err(|error| lopdf::Error::IO(std::io::Error::other(error.to_string())))?;
+
+        let crypt_filter: Arc<dyn CryptFilter> = Arc::new(Aes256CryptFilter);
+        let version = EncryptionVersion::V5 {
+            encrypt_metadata: true,
+            crypt_filters: BTreeMap::from([(b"StdCF".to_vec(), crypt_filter)]),
+            file_encryption_key: &file_encryption_key,
+            stream_filter: b"StdCF".to_vec(),
+            string_filter: b"StdCF".to_vec(),
+            owner_password: &self.owner_password,
+            user_password: &self.user_password,
+            permissions: self.permissions.into(),
+        };
+        let state = EncryptionState::try_from(version)?;
+        document.encrypt(&state)
+    }
+}
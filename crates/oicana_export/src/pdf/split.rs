@@ -0,0 +1,109 @@
+use std::num::NonZeroUsize;
+
+use oicana_world::diagnostics::TemplateDiagnostics;
+use typst::foundations::{Label, Selector, Value};
+use typst::introspection::MetadataElem;
+use typst::layout::{PageRanges, PagedDocument};
+use typst::utils::PicoStr;
+
+use crate::pdf::{export_pdf_with_standards, PdfExportOptions};
+
+/// One PDF of a split document.
+#[derive(Debug, Clone)]
+pub struct SplitPdf {
+    /// Name of this part of the document.
+    pub name: String,
+    /// The exported PDF.
+    pub pdf: Vec<u8>,
+}
+
+/// Split the document into one PDF per marked section.
+///
+/// Every element with the given label starts a new PDF on its page. Pages before the first
+/// marker belong to the first PDF. A string value of a labelled `metadata` element names its
+/// PDF, for example `#metadata("jane-doe") <split>`. Other parts are named by their position,
+/// starting at "1". If multiple markers are on the same page, only the first is used.
+///
+/// Without any marker, the whole document is exported as a single PDF.
+pub fn export_split_pdf<Diagnostics: TemplateDiagnostics>(
+    document: &PagedDocument,
+    diagnostics: &Diagnostics,
+    label: &str,
+    options: &PdfExportOptions,
+) -> Result<Vec<SplitPdf>, String> {
+    let selector = Selector::Label(Label::new(PicoStr::intern(label)));
+    let mut starts: Vec<(NonZeroUsize, Option<String>)> = Vec::new();
+    for marker in document.introspector.query(&selector) {
+        let Some(location) = marker.location() else {
+            continue;
+        };
+        let page = document.introspector.page(location);
+        if starts.last().is_some_and(|(start, _)| *start == page) {
+            continue;
+        }
+        let name = marker
+            .to_packed::<MetadataElem>()
+            .and_then(|metadata| match &metadata.value {
+                Value::Str(name) => Some(name.to_string()),
+                _ => None,
+            });
+        starts.push((page, name));
+    }
+    match starts.first_mut() {
+        Some((first, _)) => *first = NonZeroUsize::MIN,
+        None => starts.push((NonZeroUsize::MIN, None)),
+    }
+
+    let mut parts = Vec::with_capacity(starts.len());
+    for (index, (start, name)) in starts.iter().enumerate() {
+        let end = starts
+            .get(index + 1)
+            .and_then(|(next, _)| NonZeroUsize::new(next.get() - 1));
+        let pages = PageRanges::new(vec![Some(*start)..=end]);
+        let pdf =
+            export_pdf_with_standards(document, diagnostics, options.standards(), Some(pages))?;
+
+        parts.push(SplitPdf {
+            name: name.clone().unwrap_or_else(|| (index + 1).to_string()),
+            pdf: options.apply_encryption(pdf)?,
+        });
+    }
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pdf::{export_split_pdf, PdfExportOptions};
+    use crate::test_world;
+
+    #[test]
+    fn splits_at_labelled_metadata() {
+        let mut world = test_world(
+            r#"
+            Cover page
+            #pagebreak()
+            #metadata("jane") <split>
+            Dear Jane
+            #pagebreak()
+            Second page for Jane
+            #pagebreak()
+            #metadata(5) <split>
+            Dear John
+            "#,
+        );
+        let document = world.compile().expect("Failed to compile").document;
+
+        let parts = export_split_pdf(&document, &world, "split", &PdfExportOptions::default())
+            .expect("Failed to split PDF");
+
+        let parts: Vec<(String, usize)> = parts
+            .into_iter()
+            .map(|part| {
+                let pdf = lopdf::Document::load_mem(&part.pdf).expect("Failed to parse PDF");
+                (part.name, pdf.get_pages().len())
+            })
+            .collect();
+        assert_eq!(parts, [("jane".to_owned(), 3), ("2".to_owned(), 1)]);
+    }
+}
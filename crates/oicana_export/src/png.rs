@@ -1,10 +1,245 @@
-use typst::layout::{Abs, PagedDocument};
+use std::io::Write;
+
+use thiserror::Error;
+use typst::layout::{Abs, Page, PagedDocument};
+
+use crate::pages::{demultiply, ImageExportOptions, NoPagesSelected};
 
 pub use png::EncodingError;
 
+/// Failed to export the selected pages of a document as PNG.
+#[derive(Debug, Error)]
+pub enum PngExportError {
+    /// The page selection is empty.
+    #[error(transparent)]
+    NoPagesSelected(#[from] NoPagesSelected),
+    /// The image could not be encoded.
+    #[error("Failed to encode PNG: {0}")]
+    Encoding(#[from] EncodingError),
+}
+
 pub fn export_merged_png(
     document: &PagedDocument,
     pixels_per_pt: f32,
 ) -> Result<Vec<u8>, EncodingError> {
-    typst_render::render_merged(document, pixels_per_pt, Abs::pt(15.), None).encode_png()
+    let mut buffer = Vec::new();
+    write_merged_png(document, pixels_per_pt, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Export all pages of the document into a single PNG image written into the given writer.
+pub fn write_merged_png<Writer: Write>(
+    document: &PagedDocument,
+    pixels_per_pt: f32,
+    writer: &mut Writer,
+) -> Result<(), EncodingError> {
+    let pixmap = typst_render::render_merged(document, pixels_per_pt, Abs::pt(15.), None);
+
+    encode(pixmap.width(), pixmap.height(), pixmap.data(), writer)
+}
+
+/// Export the selected pages of the document into a single PNG image.
+///
+/// Pages are stacked vertically with the configured gap between them.
+pub fn export_png(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+) -> Result<Vec<u8>, PngExportError> {
+    let mut buffer = Vec::new();
+    write_png(document, options, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Export the selected pages of the document into a single PNG image written into the given writer.
+///
+/// See [`export_png`].
+pub fn write_png<Writer: Write>(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+    writer: &mut Writer,
+) -> Result<(), PngExportError> {
+    let pixmap = typst_render::render_merged(
+        &options.selected_document(document)?,
+        options.pixels_per_pt,
+        options.gap,
+        options.background,
+    );
+
+    Ok(encode(
+        pixmap.width(),
+        pixmap.height(),
+        pixmap.data(),
+        writer,
+    )?)
+}
+
+/// Export each selected page of the document as a separate PNG image.
+///
+/// The images are returned in document order.
+pub fn export_png_pages(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+) -> Result<Vec<Vec<u8>>, PngExportError> {
+    options
+        .selected_pages(document)?
+        .iter()
+        .map(|(_, page)| Ok(export_png_page(page, options.pixels_per_pt)?))
+        .collect()
+}
+
+/// Export a single page as PNG image.
+pub fn export_png_page(page: &Page, pixels_per_pt: f32) -> Result<Vec<u8>, EncodingError> {
+    let mut buffer = Vec::new();
+    write_png_page(page, pixels_per_pt, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Export a single page as PNG image written into the given writer.
+pub fn write_png_page<Writer: Write>(
+    page: &Page,
+    pixels_per_pt: f32,
+    writer: &mut Writer,
+) -> Result<(), EncodingError> {
+    let pixmap = typst_render::render(page, pixels_per_pt);
+
+    encode(pixmap.width(), pixmap.height(), pixmap.data(), writer)
+}
+
+/// Encode premultiplied RGBA pixel data row by row.
+///
+/// Only a single row is converted at a time instead of a copy of the whole image.
+fn encode<Writer: Write>(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    writer: &mut Writer,
+) -> Result<(), EncodingError> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header()?;
+
+    let mut stream = png_writer.stream_writer()?;
+    for row in pixels.chunks_exact(width as usize * 4) {
+        stream.write_all(&demultiply(row))?;
+    }
+    stream.finish()?;
+
+    png_writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pages::{ImageExportOptions, NoPagesSelected};
+    use crate::png::{export_png, export_png_pages, write_png, PngExportError};
+    use crate::test_world;
+    use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_world::world::OicanaWorld;
+    use std::num::NonZeroUsize;
+    use typst::layout::{Abs, PageRanges};
+
+    fn world() -> OicanaWorld<PreloadedTemplate> {
+        test_world(
+            r#"
+            #set page(width: 100pt, height: 50pt)
+            One
+            #pagebreak()
+            Two
+            #pagebreak()
+            Three
+            "#,
+        )
+    }
+
+    fn dimensions(png: &[u8]) -> (u32, u32) {
+        let reader = png::Decoder::new(png)
+            .read_info()
+            .expect("Failed to decode PNG");
+        let info = reader.info();
+
+        (info.width, info.height)
+    }
+
+    #[test]
+    fn exports_one_image_per_selected_page() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = ImageExportOptions {
+            pages: Some(PageRanges::new(vec![
+                NonZeroUsize::new(2)..=NonZeroUsize::new(3),
+            ])),
+            pixels_per_pt: 2.0,
+            ..ImageExportOptions::default()
+        };
+
+        let pages = export_png_pages(&document, &options).expect("Failed to export pages");
+
+        assert_eq!(pages.len(), 2);
+        for page in pages {
+            assert_eq!(dimensions(&page), (200, 100));
+        }
+    }
+
+    #[test]
+    fn merges_selected_pages_with_gap() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = ImageExportOptions {
+            pages: Some(PageRanges::new(vec![
+                NonZeroUsize::new(1)..=NonZeroUsize::new(2),
+            ])),
+            gap: Abs::pt(10.),
+            ..ImageExportOptions::default()
+        };
+
+        let png = export_png(&document, &options).expect("Failed to export image");
+
+        assert_eq!(dimensions(&png), (100, 110));
+    }
+
+    #[test]
+    fn writes_png_into_writer() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = ImageExportOptions {
+            pages: Some(PageRanges::new(vec![
+                NonZeroUsize::new(3)..=NonZeroUsize::new(3),
+            ])),
+            ..ImageExportOptions::default()
+        };
+
+        let mut png = Vec::new();
+        write_png(&document, &options, &mut png).expect("Failed to write image");
+
+        assert_eq!(dimensions(&png), (100, 50));
+    }
+
+    #[test]
+    fn fails_for_empty_page_selection() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        for range in [
+            NonZeroUsize::new(99)..=None,
+            NonZeroUsize::new(3)..=NonZeroUsize::new(2),
+        ] {
+            let options = ImageExportOptions {
+                pages: Some(PageRanges::new(vec![range])),
+                ..ImageExportOptions::default()
+            };
+
+            assert!(matches!(
+                export_png(&document, &options),
+                Err(PngExportError::NoPagesSelected(NoPagesSelected {
+                    page_count: 3
+                }))
+            ));
+            assert!(matches!(
+                export_png_pages(&document, &options),
+                Err(PngExportError::NoPagesSelected(_))
+            ));
+        }
+    }
 }
@@ -0,0 +1,297 @@
+use std::io::Write;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use thiserror::Error;
+use typst::layout::{Page, PagedDocument};
+
+use crate::pages::{demultiply, ImageExportOptions, NoPagesSelected};
+
+pub use image::ImageError;
+
+/// Failed to export the selected pages of a document as raster image.
+#[derive(Debug, Error)]
+pub enum RasterExportError {
+    /// The page selection is empty.
+    #[error(transparent)]
+    NoPagesSelected(#[from] NoPagesSelected),
+    /// The image could not be encoded.
+    #[error("Failed to encode image: {0}")]
+    Encoding(#[from] ImageError),
+}
+
+/// Encodings for raster images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterFormat {
+    /// Lossless PNG with transparency.
+    Png,
+    /// Lossy JPEG without transparency.
+    ///
+    /// Transparent areas are rendered white.
+    Jpeg {
+        /// Quality between 1 (smallest file) and 100 (best quality).
+        quality: u8,
+    },
+    /// Lossless WebP with transparency.
+    ///
+    /// The encoder does not support lossy compression, so there is no quality setting.
+    WebP,
+}
+
+/// Options for exporting a thumbnail of the first page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbnailOptions {
+    /// Maximum width of the thumbnail in pixels.
+    pub max_width: u32,
+    /// Maximum height of the thumbnail in pixels.
+    pub max_height: u32,
+    /// Encoding of the thumbnail.
+    pub format: RasterFormat,
+}
+
+/// Export the selected pages of the document into a single raster image.
+///
+/// Pages are stacked vertically with the configured gap between them.
+pub fn export_raster(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+    format: RasterFormat,
+) -> Result<Vec<u8>, RasterExportError> {
+    let mut buffer = Vec::new();
+    write_raster(document, options, format, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Export the selected pages of the document into a single raster image written into the given writer.
+///
+/// See [`export_raster`].
+pub fn write_raster<Writer: Write>(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+    format: RasterFormat,
+    writer: &mut Writer,
+) -> Result<(), RasterExportError> {
+    let pixmap = typst_render::render_merged(
+        &options.selected_document(document)?,
+        options.pixels_per_pt,
+        options.gap,
+        options.background,
+    );
+
+    Ok(encode(
+        pixmap.width(),
+        pixmap.height(),
+        pixmap.data(),
+        format,
+        writer,
+    )?)
+}
+
+/// Export each selected page of the document as a separate raster image.
+///
+/// The images are returned in document order.
+pub fn export_raster_pages(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+    format: RasterFormat,
+) -> Result<Vec<Vec<u8>>, RasterExportError> {
+    options
+        .selected_pages(document)?
+        .iter()
+        .map(|(_, page)| Ok(export_raster_page(page, options.pixels_per_pt, format)?))
+        .collect()
+}
+
+/// Export a single page as raster image.
+pub fn export_raster_page(
+    page: &Page,
+    pixels_per_pt: f32,
+    format: RasterFormat,
+) -> Result<Vec<u8>, ImageError> {
+    let mut buffer = Vec::new();
+    write_raster_page(page, pixels_per_pt, format, &mut buffer)?;
+
+    Ok(buffer)
+}
+
+/// Export a single page as raster image written into the given writer.
+pub fn write_raster_page<Writer: Write>(
+    page: &Page,
+    pixels_per_pt: f32,
+    format: RasterFormat,
+    writer: &mut Writer,
+) -> Result<(), ImageError> {
+    let pixmap = typst_render::render(page, pixels_per_pt);
+
+    encode(
+        pixmap.width(),
+        pixmap.height(),
+        pixmap.data(),
+        format,
+        writer,
+    )
+}
+
+/// Export the first page scaled to fit into the bounding box of the thumbnail options.
+///
+/// The page is rendered directly at the target resolution and keeps its aspect ratio.
+/// Fails for documents without pages.
+pub fn export_thumbnail(
+    document: &PagedDocument,
+    options: &ThumbnailOptions,
+) -> Result<Vec<u8>, RasterExportError> {
+    let Some(page) = document.pages.first() else {
+        return Err(NoPagesSelected { page_count: 0 }.into());
+    };
+    let size = page.frame.size();
+    let pixels_per_pt = f64::min(
+        f64::from(options.max_width) / size.x.to_pt(),
+        f64::from(options.max_height) / size.y.to_pt(),
+    );
+
+    Ok(export_raster_page(
+        page,
+        pixels_per_pt as f32,
+        options.format,
+    )?)
+}
+
+/// Encode premultiplied RGBA pixel data.
+fn encode<Writer: Write>(
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    format: RasterFormat,
+    writer: &mut Writer,
+) -> Result<(), ImageError> {
+    match format {
+        RasterFormat::Png => PngEncoder::new(writer).write_image(
+            &demultiply(pixels),
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?,
+        RasterFormat::Jpeg { quality } => {
+            // Compositing premultiplied colors onto white only needs the missing coverage added.
+            let rgb: Vec<u8> = pixels
+                .chunks_exact(4)
+                .flat_map(|pixel| {
+                    pixel[..3]
+                        .iter()
+                        .map(|channel| channel.saturating_add(255 - pixel[3]))
+                })
+                .collect();
+            JpegEncoder::new_with_quality(writer, quality.clamp(1, 100)).write_image(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?
+        }
+        RasterFormat::WebP => WebPEncoder::new_lossless(writer).write_image(
+            &demultiply(pixels),
+            width,
+            height,
+            ExtendedColorType::Rgba8,
+        )?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pages::ImageExportOptions;
+    use crate::raster::{
+        export_raster, export_raster_pages, export_thumbnail, RasterExportError, RasterFormat,
+        ThumbnailOptions,
+    };
+    use crate::test_world;
+    use image::{GenericImageView, ImageFormat};
+    use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_world::world::OicanaWorld;
+    use std::num::NonZeroUsize;
+    use typst::layout::{PageRanges, PagedDocument};
+
+    fn world() -> OicanaWorld<PreloadedTemplate> {
+        test_world(
+            r#"
+            #set page(width: 100pt, height: 50pt)
+            One
+            #pagebreak()
+            Two
+            "#,
+        )
+    }
+
+    #[test]
+    fn thumbnail_fits_into_bounding_box() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = ThumbnailOptions {
+            max_width: 40,
+            max_height: 40,
+            format: RasterFormat::Jpeg { quality: 80 },
+        };
+
+        let thumbnail = export_thumbnail(&document, &options).expect("Failed to export thumbnail");
+
+        assert_eq!(
+            image::guess_format(&thumbnail).ok(),
+            Some(ImageFormat::Jpeg)
+        );
+        let thumbnail = image::load_from_memory(&thumbnail).expect("Failed to decode thumbnail");
+        assert_eq!(thumbnail.dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn exports_webp_pages() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+
+        let pages = export_raster_pages(
+            &document,
+            &ImageExportOptions::default(),
+            RasterFormat::WebP,
+        )
+        .expect("Failed to export pages");
+
+        assert_eq!(pages.len(), 2);
+        for page in pages {
+            let page = image::load_from_memory_with_format(&page, ImageFormat::WebP)
+                .expect("Failed to decode page");
+            assert_eq!(page.dimensions(), (100, 50));
+        }
+    }
+
+    #[test]
+    fn fails_for_empty_page_selection() {
+        let mut world = world();
+        let document = world.compile().expect("Failed to compile").document;
+        let options = ImageExportOptions {
+            pages: Some(PageRanges::new(vec![NonZeroUsize::new(3)..=None])),
+            ..ImageExportOptions::default()
+        };
+
+        for format in [RasterFormat::Jpeg { quality: 80 }, RasterFormat::WebP] {
+            assert!(matches!(
+                export_raster(&document, &options, format),
+                Err(RasterExportError::NoPagesSelected(_))
+            ));
+        }
+        assert!(matches!(
+            export_thumbnail(
+                &PagedDocument::default(),
+                &ThumbnailOptions {
+                    max_width: 40,
+                    max_height: 40,
+                    format: RasterFormat::Png,
+                }
+            ),
+            Err(RasterExportError::NoPagesSelected(_))
+        ));
+    }
+}
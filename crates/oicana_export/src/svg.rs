@@ -1,6 +1,77 @@
-use typst::layout::{Abs, PagedDocument};
+use std::io::{self, Write};
+
+use thiserror::Error;
+use typst::layout::{Abs, Page, PagedDocument};
+
+use crate::pages::{ImageExportOptions, NoPagesSelected};
+
+/// Failed to write the selected pages of a document as SVG.
+#[derive(Debug, Error)]
+pub enum SvgExportError {
+    /// The page selection is empty.
+    #[error(transparent)]
+    NoPagesSelected(#[from] NoPagesSelected),
+    /// The image could not be written.
+    #[error("Failed to write SVG: {0}")]
+    Io(#[from] io::Error),
+}
 
 pub fn export_merged_svg(document: &PagedDocument) -> Vec<u8> {
     let svg = typst_svg::svg_merged(document, Abs::pt(15.));
     svg.into_bytes()
 }
+
+/// Export all pages of the document into a single SVG image written into the given writer.
+pub fn write_merged_svg<Writer: Write>(
+    document: &PagedDocument,
+    writer: &mut Writer,
+) -> io::Result<()> {
+    writer.write_all(typst_svg::svg_merged(document, Abs::pt(15.)).as_bytes())
+}
+
+/// Export the selected pages of the document into a single SVG image.
+///
+/// The configured gap is added around and between the pages.
+pub fn export_svg(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+) -> Result<Vec<u8>, NoPagesSelected> {
+    let svg = typst_svg::svg_merged(&options.selected_document(document)?, options.gap);
+    Ok(svg.into_bytes())
+}
+
+/// Export the selected pages of the document into a single SVG image written into the given writer.
+///
+/// See [`export_svg`].
+pub fn write_svg<Writer: Write>(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+    writer: &mut Writer,
+) -> Result<(), SvgExportError> {
+    let svg = typst_svg::svg_merged(&options.selected_document(document)?, options.gap);
+    Ok(writer.write_all(svg.as_bytes())?)
+}
+
+/// Export each selected page of the document as a separate SVG image.
+///
+/// The images are returned in document order.
+pub fn export_svg_pages(
+    document: &PagedDocument,
+    options: &ImageExportOptions,
+) -> Result<Vec<Vec<u8>>, NoPagesSelected> {
+    Ok(options
+        .selected_pages(document)?
+        .iter()
+        .map(|(_, page)| export_svg_page(page))
+        .collect())
+}
+
+/// Export a single page as SVG image.
+pub fn export_svg_page(page: &Page) -> Vec<u8> {
+    typst_svg::svg(page).into_bytes()
+}
+
+/// Export a single page as SVG image written into the given writer.
+pub fn write_svg_page<Writer: Write>(page: &Page, writer: &mut Writer) -> io::Result<()> {
+    writer.write_all(typst_svg::svg(page).as_bytes())
+}
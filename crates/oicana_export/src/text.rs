@@ -0,0 +1,114 @@
+use std::io::{self, Write};
+
+use typst::layout::{Abs, Frame, FrameItem, PagedDocument, Point, Transform};
+
+/// Separates pages in [`export_text`], like a page break in plain text files.
+pub const PAGE_SEPARATOR: char = '\u{c}';
+
+/// Extract the plain text of every page.
+///
+/// Texts are in the order they were laid out, which is the reading order for the flow of a page.
+/// A line break is inserted whenever the baseline changes and a space is inserted for horizontal
+/// gaps between texts on the same line. Images and shapes are ignored.
+pub fn export_text_pages(document: &PagedDocument) -> Vec<String> {
+    document
+        .pages
+        .iter()
+        .map(|page| {
+            let mut extractor = TextExtractor::default();
+            extractor.frame(&page.frame, Transform::identity());
+            extractor.text
+        })
+        .collect()
+}
+
+/// Extract the plain text of the document.
+///
+/// Pages are separated by a form feed. See [`export_text_pages`] for details.
+pub fn export_text(document: &PagedDocument) -> String {
+    export_text_pages(document).join(&PAGE_SEPARATOR.to_string())
+}
+
+/// Extract the plain text of the document into the given writer.
+///
+/// The text is written page by page. See [`export_text`].
+pub fn write_text<Writer: Write>(document: &PagedDocument, writer: &mut Writer) -> io::Result<()> {
+    let mut separator = [0; 4];
+    for (index, page) in document.pages.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(PAGE_SEPARATOR.encode_utf8(&mut separator).as_bytes())?;
+        }
+        let mut extractor = TextExtractor::default();
+        extractor.frame(&page.frame, Transform::identity());
+        writer.write_all(extractor.text.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct TextExtractor {
+    text: String,
+    /// Baseline and end of the last text on the page.
+    cursor: Option<(Abs, Abs)>,
+}
+
+impl TextExtractor {
+    fn frame(&mut self, frame: &Frame, ts: Transform) {
+        for (pos, item) in frame.items() {
+            let ts = ts.pre_concat(Transform::translate(pos.x, pos.y));
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame, ts.pre_concat(group.transform)),
+                FrameItem::Text(text) => {
+                    let start = Point::zero().transform(ts);
+                    if let Some((baseline, end)) = self.cursor {
+                        if (start.y - baseline).abs() > text.size * 0.5 {
+                            self.text.push('\n');
+                        } else if start.x - end > text.size * 0.2 {
+                            self.text.push(' ');
+                        }
+                    }
+                    self.text.push_str(&text.text);
+                    self.cursor = Some((start.y, start.x + text.width()));
+                }
+                FrameItem::Shape(..)
+                | FrameItem::Image(..)
+                | FrameItem::Link(..)
+                | FrameItem::Tag(..) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_world;
+    use crate::text::{export_text, export_text_pages, write_text};
+
+    #[test]
+    fn extracts_lines_and_pages() {
+        let mut world = test_world(
+            r#"
+            = Invoice
+            Hello *world*, thanks!
+
+            #table(columns: 2, [Item], [Price])
+            #pagebreak()
+            Second page
+            "#,
+        );
+        let document = world.compile().expect("Failed to compile").document;
+
+        assert_eq!(
+            export_text_pages(&document),
+            ["Invoice\nHello world, thanks!\nItem Price", "Second page"]
+        );
+        assert_eq!(
+            export_text(&document),
+            "Invoice\nHello world, thanks!\nItem Price\u{c}Second page"
+        );
+        let mut text = Vec::new();
+        write_text(&document, &mut text).expect("Failed to write text");
+        assert_eq!(text, export_text(&document).into_bytes());
+    }
+}
@@ -4,10 +4,13 @@
 //! Typst project. For development and testing, they can also be accessed as a directory
 //! in a file system, or as a map of Strings.
 
+use std::sync::Arc;
 use typst::diag::FileResult;
 use typst::foundations::Bytes;
 use typst::syntax::{FileId, Source};
 
+/// Lockfiles for the dependencies of a template.
+pub mod lock;
 /// Template files in a native file system.
 #[cfg(feature = "native")]
 pub mod native;
@@ -15,6 +18,8 @@ pub mod native;
 pub mod packed;
 /// Preloaded template files.
 pub mod preloaded;
+/// Signatures of packed templates.
+pub mod signature;
 
 /// Access files in an Oicana template.
 ///
@@ -28,6 +33,32 @@ pub trait TemplateFiles: Send + Sync {
     /// Try to access the specified file.
     fn file(&self, id: FileId) -> FileResult<Bytes>;
 
+    /// Try to access the specified file without adding it to a cache.
+    ///
+    /// Use this for files that are read once and then dropped, like font files that are only
+    /// indexed. By default, this is the same as [`Self::file`].
+    fn file_uncached(&self, id: FileId) -> FileResult<Bytes> {
+        self.file(id)
+    }
+
     /// Search for all font files in the template
     fn font_files(&self) -> &Vec<FileId>;
 }
+
+impl<Files: TemplateFiles + ?Sized> TemplateFiles for Arc<Files> {
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        (**self).source(id)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        (**self).file(id)
+    }
+
+    fn file_uncached(&self, id: FileId) -> FileResult<Bytes> {
+        (**self).file_uncached(id)
+    }
+
+    fn font_files(&self) -> &Vec<FileId> {
+        (**self).font_files()
+    }
+}
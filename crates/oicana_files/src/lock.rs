@@ -0,0 +1,263 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Component, Path};
+use std::str::FromStr;
+use std::{fs, io};
+use thiserror::Error;
+use typst::syntax::package::PackageSpec;
+
+/// Name of the lockfile in the template root.
+pub const LOCKFILE: &str = "oicana.lock";
+
+const LOCKFILE_VERSION: u8 = 1;
+
+/// The resolved dependencies of a template with hashes of their content.
+///
+/// `oicana pack` writes the lock into the template root as [`LOCKFILE`], so it is part of the
+/// packed template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateLock {
+    /// Version of the lockfile format.
+    pub version: u8,
+    /// The locked packages, sorted by their spec.
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+/// A package in a [`TemplateLock`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedPackage {
+    /// The resolved package.
+    #[serde(
+        serialize_with = "serialize_spec",
+        deserialize_with = "deserialize_spec"
+    )]
+    pub spec: PackageSpec,
+    /// Hash of all files in the package, see [`package_hash`].
+    pub hash: String,
+}
+
+impl TemplateLock {
+    /// Create a lock for the given packages.
+    pub fn new(packages: impl IntoIterator<Item = LockedPackage>) -> Self {
+        let mut packages: Vec<_> = packages.into_iter().collect();
+        packages.sort_by_cached_key(|package| package.spec.to_string());
+
+        TemplateLock {
+            version: LOCKFILE_VERSION,
+            packages,
+        }
+    }
+
+    /// Parse the content of a lockfile.
+    pub fn from_toml(toml_content: &str) -> Result<Self, LockError> {
+        let lock: TemplateLock = toml::from_str(toml_content)?;
+        if lock.version != LOCKFILE_VERSION {
+            return Err(LockError::UnsupportedVersion(lock.version));
+        }
+
+        Ok(lock)
+    }
+
+    /// Serialize the lock as the content of a lockfile.
+    pub fn to_toml(&self) -> String {
+        let lock = toml::to_string(self).expect("A template lock can always be serialized");
+
+        format!("# This file is generated by `oicana pack`. Do not edit it manually.\n\n{lock}")
+    }
+
+    /// Check that the resolved packages are exactly the locked packages with the same content.
+    pub fn verify(&self, resolved: &TemplateLock) -> Result<(), LockError> {
+        for package in &resolved.packages {
+            let Some(locked) = self
+                .packages
+                .iter()
+                .find(|locked| locked.spec == package.spec)
+            else {
+                return Err(LockError::Unlocked(package.spec.clone()));
+            };
+            if locked.hash != package.hash {
+                return Err(LockError::Changed {
+                    spec: package.spec.clone(),
+                    locked: locked.hash.clone(),
+                    resolved: package.hash.clone(),
+                });
+            }
+        }
+        if let Some(locked) = self.packages.iter().find(|locked| {
+            !resolved
+                .packages
+                .iter()
+                .any(|package| package.spec == locked.spec)
+        }) {
+            return Err(LockError::Missing(locked.spec.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Hash the files of a package.
+///
+/// The files are given with their path relative to the package root, using `/` as separator.
+/// The hash does not depend on the order of the files.
+pub fn package_hash<'a>(files: impl IntoIterator<Item = (&'a str, &'a [u8])>) -> String {
+    let files: BTreeMap<_, _> = files.into_iter().collect();
+    let mut hasher = Sha256::new();
+    for (path, content) in files {
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(content);
+    }
+
+    let mut hash = String::from("sha256:");
+    for byte in hasher.finalize() {
+        write!(hash, "{byte:02x}").expect("Writing to a string cannot fail");
+    }
+
+    hash
+}
+
+/// Hash the files of a package in the given directory, see [`package_hash`].
+pub fn hash_package_dir(dir: &Path) -> io::Result<String> {
+    fn read_files(root: &Path, dir: &Path, files: &mut Vec<(String, Vec<u8>)>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                read_files(root, &path, files)?;
+            } else if path.is_file() {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                files.push((slash_path(relative), fs::read(&path)?));
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = vec![];
+    read_files(dir, dir, &mut files)?;
+
+    Ok(package_hash(files.iter().map(|(path, content)| {
+        (path.as_str(), content.as_slice())
+    })))
+}
+
+/// A relative path with `/` as separator on all platforms.
+pub(crate) fn slash_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn serialize_spec<S: Serializer>(spec: &PackageSpec, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(spec)
+}
+
+fn deserialize_spec<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PackageSpec, D::Error> {
+    let spec = String::deserialize(deserializer)?;
+    PackageSpec::from_str(&spec).map_err(de::Error::custom)
+}
+
+/// Errors when checking dependencies against a lockfile.
+#[derive(Debug, Error)]
+pub enum LockError {
+    /// The template does not contain a lockfile.
+    #[error("The template has no lockfile '{LOCKFILE}'")]
+    NoLockfile,
+    /// The lockfile could not be parsed.
+    #[error("Failed to parse the lockfile '{LOCKFILE}': {0}")]
+    InvalidLockfile(#[from] toml::de::Error),
+    /// The lockfile was written in a newer format.
+    #[error("Unsupported lockfile version {0}")]
+    UnsupportedVersion(u8),
+    /// A package is used, but not in the lockfile.
+    #[error("Package {0} is not in the lockfile")]
+    Unlocked(PackageSpec),
+    /// A package in the lockfile is no longer used.
+    #[error("Package {0} is in the lockfile, but not a dependency of the template")]
+    Missing(PackageSpec),
+    /// The content of a package differs from the lockfile.
+    #[error("Package {spec} differs from the lockfile (locked {locked}, resolved {resolved})")]
+    Changed {
+        /// The changed package.
+        spec: PackageSpec,
+        /// Hash in the lockfile.
+        locked: String,
+        /// Hash of the resolved package.
+        resolved: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lock::{package_hash, LockError, LockedPackage, TemplateLock};
+    use std::str::FromStr;
+    use typst::syntax::package::PackageSpec;
+
+    fn locked(spec: &str, hash: &str) -> LockedPackage {
+        LockedPackage {
+            spec: PackageSpec::from_str(spec).unwrap(),
+            hash: hash.to_owned(),
+        }
+    }
+
+    #[test]
+    fn package_hash_ignores_file_order() {
+        let hash = package_hash([("lib.typ", b"#let a = 1".as_slice()), ("typst.toml", b"")]);
+
+        assert_eq!(
+            hash,
+            package_hash([("typst.toml", b"".as_slice()), ("lib.typ", b"#let a = 1")])
+        );
+        assert_ne!(
+            hash,
+            package_hash([("lib.typ", b"#let a = 2".as_slice()), ("typst.toml", b"")])
+        );
+        assert!(hash.starts_with("sha256:"));
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let lock = TemplateLock::new([
+            locked("@preview/tablex:0.0.9", "sha256:b"),
+            locked("@preview/cetz:0.3.4", "sha256:a"),
+        ]);
+
+        let parsed = TemplateLock::from_toml(&lock.to_toml()).unwrap();
+
+        assert_eq!(parsed, lock);
+        assert_eq!(parsed.packages[0].spec.name, "cetz");
+    }
+
+    #[test]
+    fn detects_differences() {
+        let lock = TemplateLock::new([locked("@preview/cetz:0.3.4", "sha256:a")]);
+
+        assert!(lock.verify(&lock).is_ok());
+        assert!(matches!(
+            lock.verify(&TemplateLock::new([locked(
+                "@preview/cetz:0.3.4",
+                "sha256:b"
+            )])),
+            Err(LockError::Changed { .. })
+        ));
+        assert!(matches!(
+            lock.verify(&TemplateLock::new([
+                locked("@preview/cetz:0.3.4", "sha256:a"),
+                locked("@preview/tablex:0.0.9", "sha256:b")
+            ])),
+            Err(LockError::Unlocked(_))
+        ));
+        assert!(matches!(
+            lock.verify(&TemplateLock::new([])),
+            Err(LockError::Missing(_))
+        ));
+    }
+}
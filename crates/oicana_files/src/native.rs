@@ -1,7 +1,6 @@
 /// Part of this code and the submodules is from the Typst CLI implementation
 /// for file access in a Typst World. Used under its MIT License.
 use crate::TemplateFiles;
-use download::PrintDownload;
 use log::debug;
 use std::collections::HashMap;
 use std::fs::{create_dir_all, ReadDir};
@@ -13,11 +12,15 @@ use typst::foundations::Bytes;
 use typst::syntax::package::PackageSpec;
 use typst::syntax::{FileId, Source, VirtualPath};
 use typst_kit::download::Downloader;
-use typst_kit::package::{PackageStorage, DEFAULT_NAMESPACE};
 
 mod download;
+mod resolver;
 mod terminal;
 
+pub use resolver::{
+    DirectoryResolver, HttpResolver, PackageRegistry, PackageResolver, TypstPackageResolver,
+};
+
 /// An Oicana template in a native file system.
 ///
 /// This is mostly used for testing and for template development.
@@ -27,25 +30,24 @@ pub struct NativeTemplate {
     pub slots: Mutex<HashMap<FileId, FileSlot>>,
     root: PathBuf,
     fonts: Vec<FileId>,
-    package_storage: PackageStorage,
-    packages: PathBuf,
+    resolver: Box<dyn PackageResolver>,
 }
 
 impl NativeTemplate {
     /// Create a new template at the given path.
-    pub fn new(root: &Path, packages: PathBuf) -> Self {
-        debug!("Packages are globally stored at {packages:?}.");
+    ///
+    /// Packages are resolved like the Typst CLI does, see [`TypstPackageResolver`].
+    pub fn new(root: &Path, packages: PathBuf, registry: PackageRegistry) -> Self {
+        Self::with_resolver(root, TypstPackageResolver::new(packages, registry))
+    }
 
+    /// Create a new template at the given path, resolving packages with the given resolver.
+    pub fn with_resolver(root: &Path, resolver: impl PackageResolver + 'static) -> Self {
         NativeTemplate {
             root: Path::new(root).to_owned(),
             slots: Mutex::new(HashMap::new()),
             fonts: find_fonts(root),
-            package_storage: PackageStorage::new(
-                Some(packages.clone()),
-                Some(packages.clone()),
-                downloader(),
-            ),
-            packages,
+            resolver: Box::new(resolver),
         }
     }
 
@@ -264,29 +266,14 @@ pub fn prepare_package(spec: &PackageSpec, files: &NativeTemplate) -> Result<Pat
         return Ok(package_dir);
     }
 
-    if spec.namespace == DEFAULT_NAMESPACE {
-        // Download preview package from network if it doesn't exist yet.
-        let cached_package = files
-            .package_storage
-            .prepare_package(spec, &mut PrintDownload(&spec))
-            .map_err(FileError::Package)?;
-        debug!("Copying {spec} from {cached_package:?}.");
-        copy_directory(cached_package, package_dir.clone())
-            .map_err(|io_error| PackageError::Other(Some(io_error.to_string().into())))?;
-        return Ok(package_dir);
-    }
-
-    let local_package = files
-        .packages
-        .join(format!("{}/{}/{}", spec.namespace, spec.name, spec.version));
-    if local_package.is_dir() {
-        debug!("Copying {spec} from {local_package:?}.");
-        copy_directory(local_package, package_dir.clone())
-            .map_err(|io_error| PackageError::Other(Some(io_error.to_string().into())))?;
-        return Ok(package_dir);
-    }
+    let Some(package) = files.resolver.resolve(spec)? else {
+        return Err(FileError::Package(PackageError::NotFound(spec.clone())));
+    };
+    debug!("Copying {spec} from {package:?}.");
+    copy_directory(package, package_dir.clone())
+        .map_err(|io_error| PackageError::Other(Some(io_error.to_string().into())))?;
 
-    Err(FileError::Package(PackageError::NotFound(spec.clone())))
+    Ok(package_dir)
 }
 
 fn copy_directory(in_dir: PathBuf, out_dir: PathBuf) -> io::Result<()> {
@@ -306,10 +293,17 @@ fn copy_directory(in_dir: PathBuf, out_dir: PathBuf) -> io::Result<()> {
 
 #[cfg(test)]
 mod tests {
-    use crate::native::copy_directory;
+    use crate::native::{copy_directory, NativeTemplate, PackageRegistry};
+    use crate::TemplateFiles;
     use std::fs::{create_dir_all, File};
-    use std::io::Write;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
     use tempfile::tempdir;
+    use typst::diag::{FileError, PackageError};
+    use typst::syntax::package::PackageSpec;
+    use typst::syntax::{FileId, VirtualPath};
 
     #[test]
     fn copy_dir() {
@@ -345,4 +339,108 @@ mod tests {
         assert!(out_dir.join("test").join("src").exists());
         assert!(out_dir.join("my-temporary-note.txt").exists());
     }
+
+    fn package_file(spec: &str, path: &str) -> FileId {
+        let spec = PackageSpec::from_str(spec).unwrap();
+        FileId::new(Some(spec), VirtualPath::new(path))
+    }
+
+    fn package_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn downloads_packages_from_mirror() {
+        let tempdir = tempdir().unwrap();
+        let root = tempdir.path().join("template");
+        create_dir_all(&root).unwrap();
+        let packages = tempdir.path().join("packages");
+        let archive = package_archive(&[
+            (
+                "typst.toml",
+                "[package]\nname = \"greeting\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"",
+            ),
+            ("lib.typ", "#let greeting = \"Hello from the mirror\""),
+        ]);
+
+        // A stand-in for an internal registry mirror, answering a single request.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                archive.len()
+            )
+            .unwrap();
+            stream.write_all(&archive).unwrap();
+
+            request_line
+        });
+
+        let files = NativeTemplate::new(
+            &root,
+            packages.clone(),
+            PackageRegistry::Mirror(format!("http://{address}/")),
+        );
+        let source = files
+            .source(package_file("@preview/greeting:0.1.0", "lib.typ"))
+            .unwrap();
+
+        assert!(source.text().contains("Hello from the mirror"));
+        assert!(server
+            .join()
+            .unwrap()
+            .starts_with("GET /preview/greeting-0.1.0.tar.gz "));
+        assert!(packages.join("preview/greeting/0.1.0/lib.typ").is_file());
+        assert!(root
+            .join(".dependencies/preview/greeting/0.1.0/lib.typ")
+            .is_file());
+    }
+
+    #[test]
+    fn offline_mode_only_uses_local_packages() {
+        let tempdir = tempdir().unwrap();
+        let root = tempdir.path().join("template");
+        create_dir_all(&root).unwrap();
+        let packages = tempdir.path().join("packages");
+        let local_package = packages.join("preview/local/0.1.0");
+        create_dir_all(&local_package).unwrap();
+        File::create(local_package.join("lib.typ"))
+            .unwrap()
+            .write_all(b"#let local = true")
+            .unwrap();
+
+        let files = NativeTemplate::new(&root, packages, PackageRegistry::Offline);
+
+        assert!(files
+            .source(package_file("@preview/local:0.1.0", "lib.typ"))
+            .is_ok());
+        let Err(FileError::Package(PackageError::Other(Some(message)))) =
+            files.source(package_file("@preview/remote:0.1.0", "lib.typ"))
+        else {
+            panic!("Expected an error for a package that is not available offline");
+        };
+        assert!(message.contains("offline mode"));
+    }
 }
@@ -0,0 +1,279 @@
+use crate::native::download::PrintDownload;
+use crate::native::downloader;
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use typst::diag::{PackageError, PackageResult};
+use typst::ecow::eco_format;
+use typst::syntax::package::PackageSpec;
+use typst_kit::package::{PackageStorage, DEFAULT_NAMESPACE};
+
+/// Finds packages imported by a native template.
+///
+/// Resolved packages are copied into the `.dependencies` directory of the template.
+/// Resolvers can be chained in a [`Vec`], the first resolver that finds a package wins.
+pub trait PackageResolver: Send + Sync {
+    /// Make the package available in a directory on the file system and return that directory.
+    ///
+    /// Returns `Ok(None)` if the package is unknown to this resolver.
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>>;
+}
+
+impl<R: PackageResolver + ?Sized> PackageResolver for Box<R> {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        (**self).resolve(spec)
+    }
+}
+
+impl<R: PackageResolver + ?Sized> PackageResolver for Arc<R> {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        (**self).resolve(spec)
+    }
+}
+
+impl<R: PackageResolver> PackageResolver for Vec<R> {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        for resolver in self {
+            if let Some(package) = resolver.resolve(spec)? {
+                return Ok(Some(package));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+/// Where missing packages of the `@preview` namespace are downloaded from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PackageRegistry {
+    /// The public Typst package registry.
+    #[default]
+    Default,
+    /// A mirror of the Typst package registry at the given URL.
+    ///
+    /// Packages are expected at `<url>/<namespace>/<name>-<version>.tar.gz`.
+    Mirror(String),
+    /// Do not download packages. Missing packages are an error.
+    Offline,
+}
+
+/// Resolves packages like the Typst CLI.
+///
+/// Packages of the `@preview` namespace are downloaded from the given registry into the package
+/// directory. Packages of other namespaces are expected in the package directory.
+pub struct TypstPackageResolver {
+    packages: PathBuf,
+    registry: PackageRegistry,
+    storage: PackageStorage,
+}
+
+impl TypstPackageResolver {
+    /// Create a resolver for the given package directory.
+    pub fn new(packages: PathBuf, registry: PackageRegistry) -> Self {
+        debug!("Packages are globally stored at {packages:?}.");
+
+        TypstPackageResolver {
+            storage: PackageStorage::new(
+                Some(packages.clone()),
+                Some(packages.clone()),
+                downloader(),
+            ),
+            packages,
+            registry,
+        }
+    }
+}
+
+impl PackageResolver for TypstPackageResolver {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        if spec.namespace != DEFAULT_NAMESPACE {
+            return DirectoryResolver::new(&self.packages).resolve(spec);
+        }
+
+        match self.registry {
+            PackageRegistry::Default => self
+                .storage
+                .prepare_package(spec, &mut PrintDownload(&spec))
+                .map(Some),
+            PackageRegistry::Mirror(ref url) => {
+                download_package(url, spec, &package_dir(&self.packages, spec)).map(Some)
+            }
+            PackageRegistry::Offline => {
+                match DirectoryResolver::new(&self.packages).resolve(spec)? {
+                    Some(package) => Ok(Some(package)),
+                    None => Err(PackageError::Other(Some(eco_format!(
+                        "{spec} is not in {} and downloading packages is disabled in offline mode",
+                        self.packages.display()
+                    )))),
+                }
+            }
+        }
+    }
+}
+
+/// Resolves packages from a directory with the layout `<namespace>/<name>/<version>`.
+///
+/// This is the layout of the Typst package directories, but it works just as well for a checked
+/// out repository of company packages.
+pub struct DirectoryResolver {
+    directory: PathBuf,
+}
+
+impl DirectoryResolver {
+    /// Create a resolver for packages in the given directory.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        DirectoryResolver {
+            directory: directory.into(),
+        }
+    }
+}
+
+impl PackageResolver for DirectoryResolver {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        let package = package_dir(&self.directory, spec);
+
+        Ok(package.is_dir().then_some(package))
+    }
+}
+
+/// Downloads packages of all namespaces from a server.
+///
+/// Packages are expected at `<base_url>/<namespace>/<name>-<version>.tar.gz`, the layout of the
+/// Typst package registry. Downloaded packages are cached in the given directory.
+/// A failed download is an error, so this resolver should be the last one in a chain.
+pub struct HttpResolver {
+    base_url: String,
+    cache: PathBuf,
+}
+
+impl HttpResolver {
+    /// Create a resolver downloading from the base URL into the cache directory.
+    pub fn new(base_url: impl Into<String>, cache: impl Into<PathBuf>) -> Self {
+        HttpResolver {
+            base_url: base_url.into(),
+            cache: cache.into(),
+        }
+    }
+}
+
+impl PackageResolver for HttpResolver {
+    fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+        download_package(&self.base_url, spec, &package_dir(&self.cache, spec)).map(Some)
+    }
+}
+
+fn package_dir(directory: &Path, spec: &PackageSpec) -> PathBuf {
+    directory.join(format!("{}/{}/{}", spec.namespace, spec.name, spec.version))
+}
+
+/// Download a package into the given directory, if it is not there yet.
+fn download_package(
+    base_url: &str,
+    spec: &PackageSpec,
+    package_dir: &Path,
+) -> PackageResult<PathBuf> {
+    if package_dir.exists() {
+        return Ok(package_dir.to_path_buf());
+    }
+
+    let url = format!(
+        "{}/{}/{}-{}.tar.gz",
+        base_url.trim_end_matches('/'),
+        spec.namespace,
+        spec.name,
+        spec.version
+    );
+    debug!("Downloading {spec} from {url}.");
+    let data = downloader()
+        .download_with_progress(&url, &mut PrintDownload(&spec))
+        .map_err(|error| PackageError::NetworkFailed(Some(eco_format!("{url}: {error}"))))?;
+
+    // Unpack next to the package directory and move it into place afterwards. An interrupted
+    // download never leaves an incomplete package behind that looks like a complete one.
+    let parent = package_dir
+        .parent()
+        .expect("Package directories are nested in the cache");
+    let unpacked = fs::create_dir_all(parent)
+        .and_then(|()| tempfile::tempdir_in(parent))
+        .map_err(|error| PackageError::Other(Some(eco_format!("{error}"))))?;
+    let decompressed = flate2::read::GzDecoder::new(data.as_slice());
+    tar::Archive::new(decompressed)
+        .unpack(unpacked.path())
+        .map_err(|error| PackageError::MalformedArchive(Some(eco_format!("{error}"))))?;
+    if let Err(error) = fs::rename(unpacked.path(), package_dir) {
+        // Another process might have downloaded the same package in the meantime.
+        if !package_dir.exists() {
+            return Err(PackageError::Other(Some(eco_format!("{error}"))));
+        }
+    }
+
+    Ok(package_dir.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::native::{DirectoryResolver, HttpResolver, PackageResolver};
+    use std::fs::{create_dir_all, read_dir};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::str::FromStr;
+    use std::thread;
+    use tempfile::tempdir;
+    use typst::syntax::package::PackageSpec;
+
+    #[test]
+    fn chained_resolvers_use_the_first_match() {
+        let tempdir = tempdir().unwrap();
+        let company = tempdir.path().join("company");
+        let packages = tempdir.path().join("packages");
+        create_dir_all(company.join("acme/letter/1.0.0")).unwrap();
+        create_dir_all(packages.join("acme/letter/1.0.0")).unwrap();
+        create_dir_all(packages.join("local/table/0.2.0")).unwrap();
+        let resolver = vec![
+            DirectoryResolver::new(&company),
+            DirectoryResolver::new(&packages),
+        ];
+
+        let resolve = |spec: &str| resolver.resolve(&PackageSpec::from_str(spec).unwrap());
+        assert_eq!(
+            resolve("@acme/letter:1.0.0").unwrap(),
+            Some(company.join("acme/letter/1.0.0"))
+        );
+        assert_eq!(
+            resolve("@local/table:0.2.0").unwrap(),
+            Some(packages.join("local/table/0.2.0"))
+        );
+        assert_eq!(resolve("@acme/letter:2.0.0").unwrap(), None);
+    }
+
+    #[test]
+    fn malformed_download_leaves_no_package_behind() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // Read the request before answering it
+            BufReader::new(&stream)
+                .lines()
+                .map_while(Result::ok)
+                .take_while(|line| !line.is_empty())
+                .for_each(drop);
+            let body = "not a tar.gz archive";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+        let tempdir = tempdir().unwrap();
+        let resolver = HttpResolver::new(base_url, tempdir.path());
+
+        let result = resolver.resolve(&PackageSpec::from_str("@acme/letter:1.0.0").unwrap());
+
+        assert!(result.is_err());
+        let versions = tempdir.path().join("acme/letter");
+        assert_eq!(read_dir(versions).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::packed::{entry_path, file_id, is_font, PackedTemplateError};
+use crate::signature::{SignatureError, TrustedKeys};
+use crate::TemplateFiles;
+use log::trace;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek};
+use std::str;
+use std::sync::Mutex;
+use typst::diag::{FileError, FileResult};
+use typst::foundations::Bytes;
+use typst::syntax::{FileId, Source};
+use zip::ZipArchive;
+
+/// A packed template that decompresses its files on first access.
+///
+/// Only the compressed archive is kept in memory. Decompressed files are cached until the total
+/// size of the cache exceeds the memory limit. Then the least recently used files are evicted
+/// and decompressed again on their next access.
+///
+/// Unlike [`super::PackedTemplate`], a [`Source`] is only created for files that are compiled as
+/// Typst.
+pub struct LazyPackedTemplate<R> {
+    archive: Mutex<ZipArchive<R>>,
+    entries: HashMap<FileId, usize>,
+    fonts: Vec<FileId>,
+    cache: Mutex<EntryCache>,
+}
+
+impl<R: Read + Seek> LazyPackedTemplate<R> {
+    /// Create a new lazy packed template from a reader of a zip file.
+    ///
+    /// Only the directory of the archive is read. Decompressed files are cached up to
+    /// `memory_limit` bytes.
+    pub fn try_new(reader: R, memory_limit: usize) -> Result<Self, PackedTemplateError> {
+        let mut archive = ZipArchive::new(reader).map_err(PackedTemplateError::NotAZip)?;
+
+        let mut entries = HashMap::new();
+        let mut fonts = vec![];
+        for index in 0..archive.len() {
+            let entry = archive.by_index_raw(index).map_err(|source| {
+                PackedTemplateError::CorruptEntry {
+                    path: format!("#{index}"),
+                    source,
+                }
+            })?;
+            if !entry.is_file() {
+                continue;
+            }
+            let path = entry.name();
+            let id = file_id(path)?;
+            if is_font(path) {
+                fonts.push(id);
+            }
+            entries.insert(id, index);
+        }
+
+        Ok(LazyPackedTemplate {
+            archive: Mutex::new(archive),
+            entries,
+            fonts,
+            cache: Mutex::new(EntryCache::new(memory_limit)),
+        })
+    }
+
+    /// Try to create a new lazy packed template and verify that it is signed by one of the
+    /// trusted keys.
+    ///
+    /// Unsigned templates and templates modified after signing are rejected.
+    /// See [`Self::verify_signature`].
+    pub fn try_new_signed(
+        reader: R,
+        memory_limit: usize,
+        trusted_keys: &TrustedKeys,
+    ) -> Result<Self, PackedTemplateError> {
+        let template = Self::try_new(reader, memory_limit)?;
+        template.verify_signature(trusted_keys)?;
+
+        Ok(template)
+    }
+
+    /// Check that the files of the template are signed by one of the trusted keys.
+    ///
+    /// All files are decompressed for the check, but they are not added to the cache.
+    pub fn verify_signature(&self, trusted_keys: &TrustedKeys) -> Result<(), SignatureError> {
+        let mut files = Vec::with_capacity(self.entries.len());
+        for id in self.entries.keys() {
+            let content = self
+                .decompress(*id)
+                .map_err(|error| io::Error::other(error.to_string()))?;
+            files.push((entry_path(*id), content));
+        }
+
+        trusted_keys.verify(
+            files
+                .iter()
+                .map(|(path, content)| (path.as_str(), content.as_slice())),
+        )
+    }
+
+    /// Total size of the decompressed files that are currently cached.
+    pub fn cached_bytes(&self) -> usize {
+        self.cache.lock().unwrap().size
+    }
+
+    fn decompress(&self, id: FileId) -> FileResult<Bytes> {
+        let not_found = || FileError::NotFound(id.vpath().as_rooted_path().to_path_buf());
+        let index = *self.entries.get(&id).ok_or_else(not_found)?;
+        let mut archive = self.archive.lock().unwrap();
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|error| FileError::Other(Some(error.to_string().into())))?;
+
+        let mut buffer = Vec::with_capacity(8192);
+        entry
+            .read_to_end(&mut buffer)
+            .map_err(|error| FileError::Other(Some(error.to_string().into())))?;
+        trace!("Decompressed {:?} ({} bytes)", id.vpath(), buffer.len());
+
+        Ok(Bytes::new(buffer))
+    }
+}
+
+impl<R: Read + Seek + Send> TemplateFiles for LazyPackedTemplate<R> {
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(source) = cache.get(id).and_then(|entry| entry.source.clone()) {
+            return Ok(source);
+        }
+
+        let bytes = match cache.get(id) {
+            Some(entry) => entry.bytes.clone(),
+            None => self.decompress(id)?,
+        };
+        let text = str::from_utf8(&bytes).map_err(|_| FileError::InvalidUtf8)?;
+        let source = Source::new(id, text.to_owned());
+        cache.insert(id, bytes, Some(source.clone()));
+
+        Ok(source)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(entry) = cache.get(id) {
+            return Ok(entry.bytes.clone());
+        }
+
+        let bytes = self.decompress(id)?;
+        cache.insert(id, bytes.clone(), None);
+
+        Ok(bytes)
+    }
+
+    fn file_uncached(&self, id: FileId) -> FileResult<Bytes> {
+        if let Some(entry) = self.cache.lock().unwrap().get(id) {
+            return Ok(entry.bytes.clone());
+        }
+
+        self.decompress(id)
+    }
+
+    fn font_files(&self) -> &Vec<FileId> {
+        &self.fonts
+    }
+}
+
+/// Decompressed files, evicting the least recently used ones above the memory limit.
+struct EntryCache {
+    memory_limit: usize,
+    size: usize,
+    clock: u64,
+    entries: HashMap<FileId, CachedEntry>,
+}
+
+struct CachedEntry {
+    bytes: Bytes,
+    source: Option<Source>,
+    last_used: u64,
+}
+
+impl CachedEntry {
+    fn size(&self) -> usize {
+        self.bytes.len() + self.source.as_ref().map_or(0, |source| source.text().len())
+    }
+}
+
+impl EntryCache {
+    fn new(memory_limit: usize) -> Self {
+        EntryCache {
+            memory_limit,
+            size: 0,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, id: FileId) -> Option<&CachedEntry> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(&id)?;
+        entry.last_used = self.clock;
+
+        Some(entry)
+    }
+
+    fn insert(&mut self, id: FileId, bytes: Bytes, source: Option<Source>) {
+        self.clock += 1;
+        let entry = CachedEntry {
+            bytes,
+            source,
+            last_used: self.clock,
+        };
+        self.size += entry.size();
+        if let Some(previous) = self.entries.insert(id, entry) {
+            self.size -= previous.size();
+        }
+
+        // The new entry is the most recently used and stays, even if it alone exceeds the limit.
+        while self.size > self.memory_limit && self.entries.len() > 1 {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+            if let Some(evicted) = self.entries.remove(&oldest) {
+                trace!("Evicting {:?} from the template cache", oldest.vpath());
+                self.size -= evicted.size();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packed::LazyPackedTemplate;
+    use crate::TemplateFiles;
+    use std::fs::read;
+    use std::io::Cursor;
+    use typst::syntax::{FileId, VirtualPath};
+
+    #[test]
+    fn reads_files_on_demand() {
+        let template =
+            read("../../assets/templates/test-0.1.0.zip").expect("Failed to read template zip");
+        let files = LazyPackedTemplate::try_new(Cursor::new(template), usize::MAX)
+            .expect("Failed to read template");
+        let id = FileId::new(None, VirtualPath::new("/template.typ"));
+
+        assert!(files.cache.lock().unwrap().entries.is_empty());
+        let source = files.source(id).expect("Failed to read source");
+        assert_eq!(
+            files.file(id).expect("Failed to read file").as_slice(),
+            source.text().as_bytes()
+        );
+        assert!(files
+            .file(FileId::new(None, VirtualPath::new("/missing.typ")))
+            .is_err());
+    }
+
+    #[test]
+    fn evicts_least_recently_used_files() {
+        let template =
+            read("../../assets/templates/fonts-0.1.0.zip").expect("Failed to read template zip");
+        let files =
+            LazyPackedTemplate::try_new(Cursor::new(template), 1).expect("Failed to read template");
+        let fonts = files.font_files().clone();
+
+        let first = files.file(fonts[0]).expect("Failed to read font");
+        files.file(fonts[1]).expect("Failed to read font");
+
+        let cache = files.cache.lock().unwrap();
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.entries.contains_key(&fonts[1]));
+        drop(cache);
+        assert_eq!(files.file(fonts[0]).expect("Failed to read font"), first);
+    }
+}
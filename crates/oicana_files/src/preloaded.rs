@@ -35,6 +35,16 @@ impl PreloadedTemplate {
             fonts: vec![],
         }
     }
+
+    /// Add a font file at the given path.
+    pub fn with_font(mut self, path: &str, data: Bytes) -> Self {
+        let id = FileId::new(None, VirtualPath::new(path));
+        self.slots
+            .insert(id, (Source::new(id, String::new()), data));
+        self.fonts.push(id);
+
+        self
+    }
 }
 
 impl TemplateFiles for PreloadedTemplate {
@@ -60,7 +70,6 @@ impl TemplateFiles for PreloadedTemplate {
             .clone())
     }
 
-    /// preloaded currently doesn't support fonts from the template
     fn font_files(&self) -> &Vec<FileId> {
         &self.fonts
     }
@@ -0,0 +1,185 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use thiserror::Error;
+use typst::syntax::package::PackageVersion;
+
+/// Path of the build metadata in a packed template.
+pub const BUILD_INFO_FILE: &str = ".oicana/build.json";
+
+/// The newest manifest version this version of Oicana can read.
+pub const SUPPORTED_MANIFEST_VERSION: u8 = 1;
+
+/// Version of Oicana, the same for all Oicana crates.
+const OICANA_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Hash of a packed file as recorded in [`BuildInfo::files`].
+pub fn file_hash(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+/// Metadata about how a template was packed.
+///
+/// [`crate::package::package`] writes it into the archive as [`BUILD_INFO_FILE`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BuildInfo {
+    /// Version of Oicana that packed the template.
+    pub oicana_version: String,
+    /// Version of Typst that Oicana used when packing the template.
+    pub typst_version: String,
+    /// Manifest version of the template.
+    pub manifest_version: u8,
+    /// Time of packing in RFC 3339 format.
+    ///
+    /// This is the time given by `SOURCE_DATE_EPOCH`. Without it, no time is recorded so that
+    /// packing the same files still results in the same archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packed_at: Option<String>,
+    /// SHA-256 hashes of all packed files by their path in the archive.
+    pub files: BTreeMap<String, String>,
+}
+
+impl BuildInfo {
+    /// Build metadata for a template packed by this version of Oicana.
+    pub fn current(
+        manifest_version: u8,
+        packed_at: Option<String>,
+        files: BTreeMap<String, String>,
+    ) -> Self {
+        BuildInfo {
+            oicana_version: OICANA_VERSION.to_owned(),
+            typst_version: PackageVersion::compiler().to_string(),
+            manifest_version,
+            packed_at,
+            files,
+        }
+    }
+
+    /// Parse the content of a [`BUILD_INFO_FILE`].
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Serialize the build metadata as the content of a [`BUILD_INFO_FILE`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Build metadata can always be serialized")
+    }
+
+    /// Check if this version of Oicana can compile the template.
+    ///
+    /// Templates packed with a newer Typst version or a newer manifest version are incompatible.
+    /// A template packed with an older Typst version can be compiled, but the output might differ,
+    /// so that is logged as a warning.
+    pub fn check_compatibility(&self) -> Result<(), IncompatibleTemplate> {
+        if self.manifest_version > SUPPORTED_MANIFEST_VERSION {
+            return Err(IncompatibleTemplate::ManifestVersion {
+                template: self.manifest_version,
+                supported: SUPPORTED_MANIFEST_VERSION,
+            });
+        }
+
+        let current = PackageVersion::compiler();
+        let typst_version: PackageVersion = self
+            .typst_version
+            .parse()
+            .map_err(|_| IncompatibleTemplate::InvalidTypstVersion(self.typst_version.clone()))?;
+        if (typst_version.major, typst_version.minor) > (current.major, current.minor) {
+            return Err(IncompatibleTemplate::NewerTypst {
+                template: self.typst_version.clone(),
+                current: current.to_string(),
+            });
+        }
+        if (typst_version.major, typst_version.minor) < (current.major, current.minor) {
+            warn!(
+                "The template was packed with Typst {typst_version}, but is compiled with Typst \
+                {current}. The output might differ from the output at packing time."
+            );
+        }
+        if self.oicana_version != OICANA_VERSION {
+            info!(
+                "The template was packed with Oicana {}, this is Oicana {OICANA_VERSION}.",
+                self.oicana_version
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// This version of Oicana cannot compile a packed template.
+#[derive(Debug, Error, PartialEq)]
+pub enum IncompatibleTemplate {
+    /// The template uses a manifest version that is not supported yet.
+    #[error(
+        "The template has manifest version {template}, but this version of Oicana only supports \
+        manifest versions up to {supported}. Please update Oicana."
+    )]
+    ManifestVersion {
+        /// Manifest version of the template.
+        template: u8,
+        /// Newest supported manifest version.
+        supported: u8,
+    },
+    /// The template was packed with a newer Typst version.
+    #[error(
+        "The template was packed with Typst {template}, but this version of Oicana uses Typst \
+        {current}. Please update Oicana."
+    )]
+    NewerTypst {
+        /// Typst version at packing time.
+        template: String,
+        /// Typst version of this Oicana version.
+        current: String,
+    },
+    /// The Typst version in the build metadata cannot be parsed.
+    #[error("Invalid Typst version '{0}' in the build metadata of the template")]
+    InvalidTypstVersion(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::build::{BuildInfo, IncompatibleTemplate};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn accepts_templates_of_this_version() {
+        let build_info = BuildInfo::current(1, None, BTreeMap::new());
+
+        assert_eq!(build_info.check_compatibility(), Ok(()));
+        assert_eq!(
+            BuildInfo::from_json(&build_info.to_json()).unwrap(),
+            build_info
+        );
+    }
+
+    #[test]
+    fn rejects_newer_templates() {
+        let build_info = BuildInfo::current(1, None, BTreeMap::new());
+
+        assert!(matches!(
+            BuildInfo {
+                manifest_version: 2,
+                ..build_info.clone()
+            }
+            .check_compatibility(),
+            Err(IncompatibleTemplate::ManifestVersion { template: 2, .. })
+        ));
+        assert!(matches!(
+            BuildInfo {
+                typst_version: "99.0.0".to_owned(),
+                ..build_info.clone()
+            }
+            .check_compatibility(),
+            Err(IncompatibleTemplate::NewerTypst { .. })
+        ));
+        assert_eq!(
+            BuildInfo {
+                typst_version: "0.1.0".to_owned(),
+                ..build_info
+            }
+            .check_compatibility(),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::fs::read_to_string;
+use std::io;
+use std::path::{Component, Path};
+
+use crate::manifest::TemplateManifest;
+
+/// Name of the optional ignore file in the template root.
+///
+/// It has the same format as a `.gitignore` file.
+pub const IGNORE_FILE: &str = ".oicanaignore";
+
+/// Glob patterns for files that are not packed.
+///
+/// The patterns follow the rules of `.gitignore` files:
+/// - `*` and `?` match any characters except `/`, `**` matches across directories
+/// - patterns without a `/` match a file or directory of that name at any depth
+/// - patterns starting with or containing a `/` are relative to the template root
+/// - patterns ending with `/` only match directories
+/// - patterns starting with `!` include paths again that an earlier pattern excluded
+///
+/// The last matching pattern wins. Excluding a directory excludes everything in it.
+#[derive(Debug, Clone, Default)]
+pub struct Excludes {
+    patterns: Vec<ExcludePattern>,
+}
+
+#[derive(Debug, Clone)]
+struct ExcludePattern {
+    glob: Vec<char>,
+    negated: bool,
+    directories_only: bool,
+}
+
+impl Excludes {
+    /// Parse exclude patterns. Empty lines and lines starting with `#` are skipped.
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a str>) -> Self {
+        let patterns = patterns
+            .into_iter()
+            .filter_map(|pattern| {
+                let pattern = pattern.trim();
+                if pattern.is_empty() || pattern.starts_with('#') {
+                    return None;
+                }
+                let (negated, pattern) = match pattern.strip_prefix('!') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern),
+                };
+                let (directories_only, pattern) = match pattern.strip_suffix('/') {
+                    Some(pattern) => (true, pattern),
+                    None => (false, pattern),
+                };
+                let glob = match pattern.strip_prefix('/') {
+                    Some(anchored) => anchored.to_owned(),
+                    None if pattern.contains('/') => pattern.to_owned(),
+                    None => format!("**/{pattern}"),
+                };
+
+                Some(ExcludePattern {
+                    glob: glob.chars().collect(),
+                    negated,
+                    directories_only,
+                })
+            })
+            .collect();
+
+        Excludes { patterns }
+    }
+
+    /// Collect the `exclude` patterns from the manifest and the patterns of the [`IGNORE_FILE`].
+    pub fn for_template(root: &Path, manifest: &TemplateManifest) -> io::Result<Self> {
+        let ignore_file = match read_to_string(root.join(IGNORE_FILE)) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Excludes::new(
+            manifest
+                .package
+                .exclude
+                .iter()
+                .map(|pattern| pattern.as_str())
+                .chain(ignore_file.lines()),
+        ))
+    }
+
+    /// Whether the path relative to the template root is excluded.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let path: Vec<char> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+            .chars()
+            .collect();
+
+        self.patterns
+            .iter()
+            .rev()
+            .filter(|pattern| is_dir || !pattern.directories_only)
+            .find(|pattern| glob_match(&pattern.glob, &path))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+}
+
+fn glob_match(glob: &[char], path: &[char]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        ['*', '*'] => true,
+        ['*', '*', '/', rest @ ..] => {
+            glob_match(rest, path)
+                || path
+                    .iter()
+                    .enumerate()
+                    .any(|(index, &char)| char == '/' && glob_match(rest, &path[index + 1..]))
+        }
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&index| index == 0 || path[index - 1] != '/')
+            .any(|index| glob_match(rest, &path[index..])),
+        ['?', rest @ ..] => {
+            matches!(path, [char, ..] if *char != '/') && glob_match(rest, &path[1..])
+        }
+        ['[', class @ ..] => {
+            let Some(end) = class.iter().skip(1).position(|&char| char == ']') else {
+                return literal_match(glob, path);
+            };
+            let (class, rest) = (&class[..end + 1], &class[end + 2..]);
+            match path {
+                [char, path_rest @ ..] if *char != '/' && class_match(class, *char) => {
+                    glob_match(rest, path_rest)
+                }
+                _ => false,
+            }
+        }
+        ['\\', escaped, rest @ ..] => {
+            matches!(path, [char, ..] if char == escaped) && glob_match(rest, &path[1..])
+        }
+        _ => literal_match(glob, path),
+    }
+}
+
+fn literal_match(glob: &[char], path: &[char]) -> bool {
+    matches!(path, [char, ..] if *char == glob[0]) && glob_match(&glob[1..], &path[1..])
+}
+
+/// Match a character class like `a-z0-9` or `!abc` (without the brackets).
+fn class_match(class: &[char], char: char) -> bool {
+    let (negated, mut class) = match class {
+        ['!' | '^', rest @ ..] => (true, rest),
+        _ => (false, class),
+    };
+    let mut matched = false;
+    while !class.is_empty() {
+        match class {
+            [start, '-', end, rest @ ..] => {
+                matched |= (*start..=*end).contains(&char);
+                class = rest;
+            }
+            [single, rest @ ..] => {
+                matched |= *single == char;
+                class = rest;
+            }
+            [] => unreachable!(),
+        }
+    }
+
+    matched != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exclude::Excludes;
+    use std::path::Path;
+
+    fn excluded(patterns: &[&str], path: &str) -> bool {
+        Excludes::new(patterns.iter().copied()).is_excluded(Path::new(path), false)
+    }
+
+    #[test]
+    fn matches_names_at_any_depth() {
+        assert!(excluded(&["*.zip"], "invoice-0.1.0.zip"));
+        assert!(excluded(&["*.zip"], "output/old/invoice.zip"));
+        assert!(excluded(&[".git"], ".git"));
+        assert!(!excluded(&["*.zip"], "zip/main.typ"));
+        assert!(!excluded(&["design.fig"], "design.figma"));
+    }
+
+    #[test]
+    fn anchors_patterns_with_slashes() {
+        assert!(excluded(&["/output"], "output"));
+        assert!(!excluded(&["/output"], "assets/output"));
+        assert!(excluded(&["assets/*.psd"], "assets/logo.psd"));
+        assert!(!excluded(&["assets/*.psd"], "assets/raw/logo.psd"));
+        assert!(excluded(&["assets/**/*.psd"], "assets/raw/logo.psd"));
+        assert!(excluded(&["assets/**/*.psd"], "assets/logo.psd"));
+        assert!(excluded(&["drafts/**"], "drafts/a/b.typ"));
+    }
+
+    #[test]
+    fn supports_negation_classes_and_directories() {
+        let excludes = Excludes::new(["# comment", "", "*.png", "!logo.png", "build/"]);
+
+        assert!(excludes.is_excluded(Path::new("screenshot.png"), false));
+        assert!(!excludes.is_excluded(Path::new("logo.png"), false));
+        assert!(excludes.is_excluded(Path::new("build"), true));
+        assert!(!excludes.is_excluded(Path::new("build"), false));
+        assert!(excluded(&["draft-[0-9].typ"], "draft-3.typ"));
+        assert!(!excluded(&["draft-[!0-9].typ"], "draft-3.typ"));
+        assert!(excluded(&["v?.typ"], "v2.typ"));
+    }
+}
@@ -14,6 +14,10 @@ use std::io;
 use std::path::PathBuf;
 use thiserror::Error;
 
+/// Build metadata of packed templates.
+pub mod build;
+/// Patterns for files that are not packed.
+pub mod exclude;
 /// Manifest of an Oicana template.
 pub mod manifest;
 /// Method to package a template.
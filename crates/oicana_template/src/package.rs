@@ -1,52 +1,120 @@
-use chrono::{Datelike, Timelike, Utc};
+use chrono::{Datelike, SecondsFormat, Timelike, Utc};
 use log::trace;
+use std::collections::BTreeMap;
 use std::fs::File;
-use std::io;
 use std::io::{Read, Seek, Write};
 use std::num::TryFromIntError;
-use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::path::{Component, Path, PathBuf};
+use std::{env, io};
 use thiserror::Error;
 use walkdir::{DirEntry, WalkDir};
 use zip::result::{DateTimeRangeError, ZipError};
 use zip::write::SimpleFileOptions;
 use zip::{CompressionMethod, DateTime, ZipWriter};
 
+use crate::build::{file_hash, BuildInfo, BUILD_INFO_FILE};
+use crate::exclude::Excludes;
 use crate::manifest::TemplateManifest;
 
 /// Package a directory as an Oicana template.
+///
+/// Packing the same files results in the same archive. Entries are sorted by their path and have
+/// normalized permissions. All entries have the timestamp given by the `SOURCE_DATE_EPOCH`
+/// environment variable, or 1980-01-01 if it is not set.
+///
+/// The archive contains [`BuildInfo`] about the packing as [`BUILD_INFO_FILE`].
 pub fn package<T>(
     src_dir: &Path,
     writer: T,
     manifest: &TemplateManifest,
+    compression: Compression,
 ) -> Result<(), PackageError>
 where
     T: Write + Seek,
 {
-    if !Path::new(src_dir).is_dir() {
-        return Err(PackageError::SourceIsNotADirectory);
-    }
-
-    let walk_dir = WalkDir::new(src_dir);
-    let it = walk_dir
-        .into_iter()
-        .filter_entry(|entry| manifest.should_path_be_packed(entry.path()));
+    let entries = packed_entries(src_dir, manifest)?;
+    let source_date_epoch = source_date_epoch(env::var("SOURCE_DATE_EPOCH").ok().as_deref())?;
 
     zip_dir(
-        &mut it.filter_map(|e| e.ok()),
+        &mut entries.into_iter(),
         src_dir,
         writer,
-        CompressionMethod::ZSTD,
+        compression.into(),
+        source_date_epoch,
+        manifest.tool.oicana.manifest_version,
     )?;
 
     Ok(())
 }
 
+/// Compression of the files in a template archive.
+///
+/// All integrations can read templates with any of these compressions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Deflate is supported by all zip tools.
+    Deflate,
+    /// Zstandard results in smaller archives, but is not supported by all zip tools.
+    #[default]
+    Zstd,
+    /// Files are not compressed.
+    Stored,
+}
+
+impl From<Compression> for CompressionMethod {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Deflate => CompressionMethod::Deflated,
+            Compression::Zstd => CompressionMethod::Zstd,
+            Compression::Stored => CompressionMethod::Stored,
+        }
+    }
+}
+
+/// Collect the files and directories of a template directory that are packed.
+///
+/// The tests directory, the `.oicana` directory, and everything matching the [`Excludes`] of the
+/// template is skipped. Dependencies in `.dependencies` are always packed completely.
+pub fn packed_entries(
+    src_dir: &Path,
+    manifest: &TemplateManifest,
+) -> Result<Vec<DirEntry>, PackageError> {
+    if !Path::new(src_dir).is_dir() {
+        return Err(PackageError::SourceIsNotADirectory);
+    }
+    let excludes = Excludes::for_template(src_dir, manifest)?;
+
+    let entries = WalkDir::new(src_dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            let Ok(path) = entry.path().strip_prefix(src_dir) else {
+                return true;
+            };
+            if path.as_os_str().is_empty() || path.starts_with(".dependencies") {
+                return true;
+            }
+            // Reserved for files written while packing
+            if path.starts_with(".oicana") {
+                return false;
+            }
+
+            manifest.should_path_be_packed(path)
+                && !excludes.is_excluded(path, entry.file_type().is_dir())
+        })
+        .filter_map(|e| e.ok())
+        .collect();
+
+    Ok(entries)
+}
+
 fn zip_dir<T>(
     it: &mut dyn Iterator<Item = DirEntry>,
     prefix: &Path,
     writer: T,
     method: CompressionMethod,
+    source_date_epoch: Option<chrono::DateTime<Utc>>,
+    manifest_version: u8,
 ) -> Result<(), PackageError>
 where
     T: Write + Seek,
@@ -54,44 +122,82 @@ where
     let mut zip = ZipWriter::new(writer);
     let options = SimpleFileOptions::default()
         .compression_method(method)
-        .unix_permissions(0o755);
+        .last_modified_time(zip_date_time(source_date_epoch)?);
+    let file_options = options.unix_permissions(0o644);
+    let directory_options = options.unix_permissions(0o755);
 
     let prefix = Path::new(prefix);
     let mut buffer = Vec::with_capacity(4096);
+    let mut hashes = BTreeMap::new();
     for entry in it {
         let path = entry.path();
         let name = path.strip_prefix(prefix).unwrap();
+        // Always use `/` as separator, independent of the platform
         let path_as_string = name
-            .to_str()
-            .map(str::to_owned)
-            .ok_or(PackageError::InvalidFilePath(name.to_path_buf()))?;
+            .components()
+            .map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(PackageError::InvalidFilePath(name.to_path_buf()))?
+            .join("/");
 
         // Write file or directory explicitly
         // Some unzip tools unzip files with directory paths correctly, some do not!
         if path.is_file() {
             trace!("adding file {path_as_string:?}");
             let mut f = File::open(path)?;
-            zip.start_file(
-                path_as_string,
-                options.last_modified_time(zip_date_from_system_time(f.metadata()?.modified()?)?),
-            )?;
+            zip.start_file(path_as_string.as_str(), file_options)?;
 
             f.read_to_end(&mut buffer)?;
             zip.write_all(&buffer)?;
+            hashes.insert(path_as_string, file_hash(&buffer));
             buffer.clear();
         } else if !name.as_os_str().is_empty() {
             // Only if not root! Avoids path spec / warning
             // and "mapname conversion failed" error on unzip
             trace!("adding dir {path_as_string:?}");
-            zip.add_directory(path_as_string, options)?;
+            zip.add_directory(path_as_string, directory_options)?;
         }
     }
+
+    let build_info = BuildInfo::current(
+        manifest_version,
+        source_date_epoch.map(|date_time| date_time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        hashes,
+    );
+    zip.add_directory(".oicana", directory_options)?;
+    zip.start_file(BUILD_INFO_FILE, file_options)?;
+    zip.write_all(build_info.to_json().as_bytes())?;
     zip.finish()?;
     Ok(())
 }
 
-fn zip_date_from_system_time(time: SystemTime) -> Result<DateTime, PackageError> {
-    let date_time = chrono::DateTime::<Utc>::from(time);
+/// Parse the value of `SOURCE_DATE_EPOCH`.
+fn source_date_epoch(
+    source_date_epoch: Option<&str>,
+) -> Result<Option<chrono::DateTime<Utc>>, PackageError> {
+    source_date_epoch
+        .map(|source_date_epoch| {
+            source_date_epoch
+                .trim()
+                .parse()
+                .ok()
+                .and_then(|seconds| chrono::DateTime::<Utc>::from_timestamp(seconds, 0))
+                .ok_or_else(|| PackageError::InvalidSourceDateEpoch(source_date_epoch.to_owned()))
+        })
+        .transpose()
+}
+
+/// The timestamp of all archive entries from the value of `SOURCE_DATE_EPOCH`.
+///
+/// Without a value, this is the earliest date a zip archive supports.
+fn zip_date_time(date_time: Option<chrono::DateTime<Utc>>) -> Result<DateTime, PackageError> {
+    let Some(date_time) = date_time else {
+        return Ok(DateTime::default());
+    };
+
     Ok(DateTime::from_date_and_time(
         date_time.year().try_into()?,
         date_time.month().try_into()?,
@@ -117,10 +223,223 @@ pub enum PackageError {
     /// Error while compressing the template.
     #[error("zip error: {0}")]
     Zip(#[from] ZipError),
-    /// Failed to convert a last modified date to a [`DateTime`].
-    #[error("failed to convert last modified dates: {0}")]
+    /// `SOURCE_DATE_EPOCH` is not a number of seconds since the Unix epoch.
+    #[error("SOURCE_DATE_EPOCH is not a valid Unix timestamp: {0:?}")]
+    InvalidSourceDateEpoch(String),
+    /// Failed to convert the timestamp to a [`DateTime`].
+    #[error("failed to convert the timestamp of the archive entries: {0}")]
     IntConversion(#[from] TryFromIntError),
-    /// A last modified date is out of range.
-    #[error("failed to convert last modified dates: {0}")]
+    /// The timestamp is out of the range supported by zip archives.
+    #[error("failed to convert the timestamp of the archive entries: {0}")]
     DateTimeRange(#[from] DateTimeRangeError),
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::build::{BuildInfo, BUILD_INFO_FILE};
+    use crate::manifest::TemplateManifest;
+    use crate::package::{package, packed_entries, source_date_epoch, zip_date_time, Compression};
+    use std::fs::{create_dir_all, write, File};
+    use std::io::{Cursor, Read};
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, SystemTime};
+    use tempfile::tempdir;
+    use zip::{CompressionMethod, DateTime};
+
+    const MANIFEST: &str = r#"
+        [package]
+        name = "invoice"
+        version = "0.1.0"
+        entrypoint = "main.typ"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#;
+
+    #[test]
+    fn skips_excluded_files() {
+        let template = tempdir().unwrap();
+        let root = template.path();
+        let manifest = r#"
+            [package]
+            name = "invoice"
+            version = "0.1.0"
+            entrypoint = "main.typ"
+            exclude = ["*.zip", "/output"]
+
+            [tool.oicana]
+            manifest_version = 1
+            "#;
+        for file in [
+            "typst.toml",
+            "main.typ",
+            "invoice-0.0.9.zip",
+            "output/invoice.pdf",
+            "design/logo.psd",
+            "design/logo.svg",
+            "tests/invoice.tests.toml",
+            ".git/HEAD",
+            ".oicana/build.json",
+            ".dependencies/preview/example/0.1.0/example.zip",
+        ] {
+            let path = root.join(file);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, manifest).unwrap();
+        }
+        write(root.join(".oicanaignore"), "# VCS\n.git/\n*.psd\n").unwrap();
+        let manifest = TemplateManifest::from_toml(manifest).unwrap();
+
+        let mut files: Vec<PathBuf> = packed_entries(root, &manifest)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.path().strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        files.sort();
+
+        assert_eq!(
+            files,
+            [
+                ".dependencies/preview/example/0.1.0/example.zip",
+                ".oicanaignore",
+                "design/logo.svg",
+                "main.typ",
+                "typst.toml",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    fn write_template(root: &Path, files: &[&str], modified: SystemTime) {
+        for file in files {
+            let path = root.join(file);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(&path, file.as_bytes()).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        }
+        write(root.join("typst.toml"), MANIFEST).unwrap();
+    }
+
+    #[test]
+    fn packs_deterministically() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        let files = ["main.typ", "b/logo.svg", "a/data.json", "b/a/nested.typ"];
+        write_template(first.path(), &files, SystemTime::UNIX_EPOCH);
+        let mut reversed = files;
+        reversed.reverse();
+        write_template(
+            second.path(),
+            &reversed,
+            SystemTime::now() - Duration::from_secs(3600),
+        );
+        let manifest = TemplateManifest::from_toml(MANIFEST).unwrap();
+
+        let mut first_archive = Cursor::new(vec![]);
+        package(
+            first.path(),
+            &mut first_archive,
+            &manifest,
+            Compression::default(),
+        )
+        .unwrap();
+        let mut second_archive = Cursor::new(vec![]);
+        package(
+            second.path(),
+            &mut second_archive,
+            &manifest,
+            Compression::default(),
+        )
+        .unwrap();
+
+        assert_eq!(first_archive.get_ref(), second_archive.get_ref());
+        let archive = zip::ZipArchive::new(first_archive).unwrap();
+        assert_eq!(
+            archive.file_names().collect::<Vec<_>>(),
+            [
+                "a/",
+                "a/data.json",
+                "b/",
+                "b/a/",
+                "b/a/nested.typ",
+                "b/logo.svg",
+                "main.typ",
+                "typst.toml",
+                ".oicana/",
+                ".oicana/build.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn uses_source_date_epoch() {
+        let source_date = |value| zip_date_time(source_date_epoch(value).unwrap());
+
+        assert_eq!(source_date(None).unwrap(), DateTime::default());
+        assert_eq!(
+            source_date(Some("1700000000")).unwrap(),
+            DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap()
+        );
+        assert!(source_date_epoch(Some("yesterday")).is_err());
+    }
+
+    #[test]
+    fn compresses_with_the_selected_method() {
+        let template = tempdir().unwrap();
+        write_template(template.path(), &["main.typ"], SystemTime::UNIX_EPOCH);
+        let manifest = TemplateManifest::from_toml(MANIFEST).unwrap();
+
+        for (compression, method) in [
+            (Compression::Deflate, CompressionMethod::Deflated),
+            (Compression::Zstd, CompressionMethod::Zstd),
+            (Compression::Stored, CompressionMethod::Stored),
+        ] {
+            let mut archive = Cursor::new(vec![]);
+            package(template.path(), &mut archive, &manifest, compression).unwrap();
+
+            let mut archive = zip::ZipArchive::new(archive).unwrap();
+            assert_eq!(archive.by_name("main.typ").unwrap().compression(), method);
+        }
+    }
+
+    #[test]
+    fn writes_build_info() {
+        let template = tempdir().unwrap();
+        write_template(template.path(), &["main.typ"], SystemTime::UNIX_EPOCH);
+        let manifest = TemplateManifest::from_toml(MANIFEST).unwrap();
+
+        let mut archive = Cursor::new(vec![]);
+        package(
+            template.path(),
+            &mut archive,
+            &manifest,
+            Compression::default(),
+        )
+        .unwrap();
+
+        let mut archive = zip::ZipArchive::new(archive).unwrap();
+        let mut build_info = String::new();
+        archive
+            .by_name(BUILD_INFO_FILE)
+            .unwrap()
+            .read_to_string(&mut build_info)
+            .unwrap();
+        let build_info = BuildInfo::from_json(&build_info).unwrap();
+        assert_eq!(build_info.manifest_version, 1);
+        assert_eq!(build_info.packed_at, None);
+        assert_eq!(
+            build_info.files.keys().collect::<Vec<_>>(),
+            ["main.typ", "typst.toml"]
+        );
+        assert_eq!(
+            build_info.files["main.typ"],
+            "sha256:0d17b34bf40cb67e2d6ed40719b4f7647179baf2cd361da9a9a3a4701d496153"
+        );
+        build_info.check_compatibility().unwrap();
+    }
+}
@@ -7,7 +7,8 @@ use image::{GenericImageView, ImageError};
 use log::warn;
 use oicana::{Template, TemplateInitializationError};
 use oicana_export::png::{export_merged_png, EncodingError};
-use oicana_files::native::{package_data_dir, NativeTemplate};
+use oicana_export::text::export_text;
+use oicana_files::native::{package_data_dir, NativeTemplate, PackageRegistry};
 use oicana_template::manifest::TemplateManifest;
 use oicana_world::{CompiledDocument, TemplateCompilationFailure};
 use thiserror::Error;
@@ -17,14 +18,15 @@ use crate::Test;
 /// Context for test runners
 pub struct TestRunnerContext {
     packages: PathBuf,
+    registry: PackageRegistry,
 }
 
 impl TestRunnerContext {
-    /// Create a new test runner
-    pub fn new() -> Result<Self, CreateTestRunnerError> {
+    /// Create a new test runner that downloads missing packages from the given registry
+    pub fn new(registry: PackageRegistry) -> Result<Self, CreateTestRunnerError> {
         let packages = package_data_dir().ok_or(CreateTestRunnerError::NoPackageDirectory)?;
 
-        Ok(TestRunnerContext { packages })
+        Ok(TestRunnerContext { packages, registry })
     }
 
     /// Prepare a runner for the template at the given path
@@ -33,9 +35,15 @@ impl TestRunnerContext {
         path: &Path,
         manifest: &TemplateManifest,
     ) -> Result<TestRunner, TemplateInitializationError> {
-        Ok(TestRunner {
-            instance: Template::<NativeTemplate>::from(path, &self.packages, manifest.clone())?,
-        })
+        let mut instance = Template::<NativeTemplate>::from(
+            path,
+            &self.packages,
+            self.registry.clone(),
+            manifest.clone(),
+        )?;
+        instance.report_fonts(true);
+
+        Ok(TestRunner { instance })
     }
 }
 
@@ -47,7 +55,12 @@ pub struct TestRunner {
 impl TestRunner {
     /// Run the test case
     pub fn run(&mut self, test: Test) -> Result<Vec<String>, TestExecutionError> {
-        let CompiledDocument { document, warnings } = self.instance.compile(test.inputs)?;
+        self.instance.requested_font_families(test.font_families);
+        let CompiledDocument {
+            document,
+            warnings,
+            fonts,
+        } = self.instance.compile(test.inputs)?;
         let mut warnings = if let Some(warning) = warnings {
             vec![warning]
         } else {
@@ -79,6 +92,44 @@ impl TestRunner {
             _ => (),
         }
 
+        if let Some(report) = fonts {
+            if !report.fallback_glyphs.is_empty() {
+                let fallback = report
+                    .fallback_glyphs
+                    .iter()
+                    .map(|fallback| {
+                        format!(
+                            "'{}' on page {} (rendered with {})",
+                            fallback.character, fallback.page, fallback.family
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if test.fail_on_missing_glyphs {
+                    return Err(TestExecutionError::FallbackGlyphs(fallback));
+                }
+                warnings.push(format!("No requested font has a glyph for {fallback}"));
+            }
+            if !report.missing_glyphs.is_empty() {
+                let missing = report
+                    .missing_glyphs
+                    .iter()
+                    .map(|missing| format!("'{}' on page {}", missing.character, missing.page))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if test.fail_on_missing_glyphs {
+                    return Err(TestExecutionError::MissingGlyphs(missing));
+                }
+                warnings.push(format!("No font has a glyph for {missing}"));
+            }
+        }
+
+        if let Some(assertion) = test.text {
+            assertion
+                .check(&export_text(&document))
+                .map_err(TestExecutionError::TextMismatch)?;
+        }
+
         Ok(warnings)
     }
 }
@@ -138,4 +189,13 @@ pub enum TestExecutionError {
     /// The snapshot does not match the result
     #[error("The snapshot does not match the result.")]
     SnapshotMismatch,
+    /// The text of the document does not match the expectations
+    #[error("The text does not match: {0}")]
+    TextMismatch(String),
+    /// Characters of the document have no glyph in the requested font families
+    #[error("No requested font has a glyph for {0}")]
+    FallbackGlyphs(String),
+    /// Characters of the document have no glyph in any available font
+    #[error("No font has a glyph for {0}")]
+    MissingGlyphs(String),
 }
@@ -37,6 +37,12 @@ pub struct Test {
     pub collection: PathBuf,
     /// Descriptor of the tests
     pub descriptor: String,
+    /// Expectations for the plain text of the compiled document
+    pub text: Option<TextAssertion>,
+    /// Font families the document is expected to be set in
+    pub font_families: Vec<String>,
+    /// Fail instead of warn if characters have no glyph in the font families or in any available font
+    pub fail_on_missing_glyphs: bool,
 }
 
 /// Test snapshot
@@ -87,6 +93,9 @@ impl Test {
                 .join(" > "),
             name: template_test.name,
             snapshot,
+            text: template_test.text,
+            font_families: template_test.font_families,
+            fail_on_missing_glyphs: template_test.fail_on_missing_glyphs,
         })
     }
 
@@ -215,6 +224,58 @@ pub struct TemplateTest {
     /// The input values for this test.
     #[serde(default = "production")]
     pub mode: CompilationMode,
+    /// Expectations for the plain text of the compiled document.
+    pub text: Option<TextAssertion>,
+    /// Font families the document is expected to be set in.
+    ///
+    /// Characters that are rendered with a fallback font, because none of these families has a
+    /// glyph for them, count as missing glyphs. Defaults to the families of the fonts in the
+    /// template.
+    #[serde(default = "Vec::new")]
+    pub font_families: Vec<String>,
+    /// Fail the test if characters have no glyph in the font families or in any available font.
+    ///
+    /// By default, missing glyphs only produce a warning.
+    #[serde(default)]
+    pub fail_on_missing_glyphs: bool,
+}
+
+/// Expectations for the plain text of a compiled document.
+///
+/// Pages are separated by a form feed in the extracted text.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TextAssertion {
+    /// The whole text of the document, ignoring leading and trailing whitespace.
+    pub equals: Option<String>,
+    /// Snippets that have to appear in the text.
+    #[serde(default = "Vec::new")]
+    pub contains: Vec<String>,
+}
+
+impl TextAssertion {
+    /// Check the given text against this assertion.
+    ///
+    /// Returns a description of the first failed expectation.
+    pub fn check(&self, text: &str) -> Result<(), String> {
+        if let Some(ref expected) = self.equals {
+            if expected.trim() != text.trim() {
+                return Err(format!(
+                    "Expected the text\n{}\nbut found\n{}",
+                    expected.trim(),
+                    text.trim()
+                ));
+            }
+        }
+        if let Some(missing) = self
+            .contains
+            .iter()
+            .find(|snippet| !text.contains(snippet.as_str()))
+        {
+            return Err(format!("Expected the text to contain '{missing}'"));
+        }
+
+        Ok(())
+    }
 }
 
 fn production() -> CompilationMode {
@@ -384,4 +445,60 @@ mod tests {
             .expect("Failed to read test collection from file");
         assert_eq!(test_collection.tests[0].mode, CompilationMode::Development);
     }
+
+    #[test]
+    fn text_assertion() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("tests.toml");
+        let mut file = File::create(&path).unwrap();
+        write!(
+            &mut file,
+            r#"
+                        tests_version = 1
+
+                        [[test]]
+                        name = "test"
+                        text.contains = ["Total", "42"]
+                        "#
+        )
+        .unwrap();
+
+        let test_collection = TemplateTestCollection::read_from(&path)
+            .expect("Failed to read test collection from file");
+        let assertion = test_collection.tests[0]
+            .text
+            .as_ref()
+            .expect("Test should have a text assertion");
+        assert!(assertion.check("Total: 42 €").is_ok());
+        assert!(assertion.check("Total: 41 €").is_err());
+    }
+
+    #[test]
+    fn missing_glyphs_are_opt_in_failures() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("tests.toml");
+        let mut file = File::create(&path).unwrap();
+        write!(
+            &mut file,
+            r#"
+                        tests_version = 1
+
+                        [[test]]
+                        name = "default"
+
+                        [[test]]
+                        name = "strict"
+                        font_families = ["Libertinus Serif"]
+                        fail_on_missing_glyphs = true
+                        "#
+        )
+        .unwrap();
+
+        let test_collection = TemplateTestCollection::read_from(&path)
+            .expect("Failed to read test collection from file");
+        assert!(!test_collection.tests[0].fail_on_missing_glyphs);
+        assert!(test_collection.tests[0].font_families.is_empty());
+        assert!(test_collection.tests[1].fail_on_missing_glyphs);
+        assert_eq!(test_collection.tests[1].font_families, ["Libertinus Serif"]);
+    }
 }
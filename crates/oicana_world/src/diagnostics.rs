@@ -140,7 +140,7 @@ impl<Files: TemplateFiles> TemplateDiagnostics for OicanaWorld<Files> {
 }
 
 /// Color mode for diagnostic logs
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DiagnosticColor {
     /// No colors in diagnostic output
     None,
@@ -0,0 +1,306 @@
+use std::fmt::{self, Display};
+
+use typst::layout::{Frame, FrameItem, PagedDocument};
+use typst::text::{FontBook, FontVariant};
+
+/// Fonts used in a compiled document and characters that the requested fonts could not render.
+///
+/// If none of the requested font families contains a glyph for a character, Typst falls back to
+/// any other available font. These characters are listed in [`Self::fallback_glyphs`].
+/// Characters without a glyph in all available fonts are rendered as a placeholder ("tofu") and
+/// listed in [`Self::missing_glyphs`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FontReport {
+    /// Fonts that text of the document is set in, in order of first use.
+    pub used: Vec<UsedFont>,
+    /// Characters without a glyph in the requested font families, that were rendered with a
+    /// fallback font, in order of first appearance.
+    pub fallback_glyphs: Vec<FallbackGlyph>,
+    /// Characters without a glyph, in order of first appearance.
+    pub missing_glyphs: Vec<MissingGlyph>,
+}
+
+/// A font used in a compiled document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsedFont {
+    /// The font family.
+    pub family: String,
+    /// Style, weight, and stretch of the font.
+    pub variant: FontVariant,
+}
+
+/// A character that was rendered with a fallback font, because none of the requested font
+/// families has a glyph for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackGlyph {
+    /// The character without a glyph in the requested font families.
+    pub character: char,
+    /// Family of the fallback font that the character was rendered with.
+    pub family: String,
+    /// The first page the character appears on, starting at 1.
+    pub page: usize,
+}
+
+/// A character that was rendered as a placeholder, because no font has a glyph for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingGlyph {
+    /// The character without a glyph.
+    pub character: char,
+    /// Family of the font that the placeholder was rendered with.
+    pub family: String,
+    /// The first page the character appears on, starting at 1.
+    pub page: usize,
+}
+
+impl FontReport {
+    /// Collect the fonts and missing glyphs of a compiled document.
+    ///
+    /// Characters set in a font outside of `families` are reported as fallback glyphs if no font
+    /// of the requested families in `book` covers them. Without requested families, only
+    /// characters without any glyph are reported.
+    pub fn new(document: &PagedDocument, book: &FontBook, families: &[String]) -> Self {
+        let requested = RequestedFamilies {
+            book,
+            families: families
+                .iter()
+                .map(|family| family.to_lowercase())
+                .collect(),
+        };
+        let mut report = FontReport::default();
+        for (index, page) in document.pages.iter().enumerate() {
+            report.frame(&page.frame, index + 1, &requested);
+        }
+
+        report
+    }
+
+    fn frame(&mut self, frame: &Frame, page: usize, requested: &RequestedFamilies) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame, page, requested),
+                FrameItem::Text(text) => {
+                    let info = text.font.info();
+                    if !self
+                        .used
+                        .iter()
+                        .any(|font| font.family == info.family && font.variant == info.variant)
+                    {
+                        self.used.push(UsedFont {
+                            family: info.family.clone(),
+                            variant: info.variant,
+                        });
+                    }
+
+                    let fallback = requested.is_fallback(&info.family);
+                    for glyph in &text.glyphs {
+                        let range = glyph.range.start as usize..glyph.range.end as usize;
+                        for character in text.text[range].chars() {
+                            if glyph.id == 0 {
+                                if !self
+                                    .missing_glyphs
+                                    .iter()
+                                    .any(|missing| missing.character == character)
+                                {
+                                    self.missing_glyphs.push(MissingGlyph {
+                                        character,
+                                        family: info.family.clone(),
+                                        page,
+                                    });
+                                }
+                            } else if fallback
+                                && !requested.covers(character)
+                                && !self
+                                    .fallback_glyphs
+                                    .iter()
+                                    .any(|fallback| fallback.character == character)
+                            {
+                                self.fallback_glyphs.push(FallbackGlyph {
+                                    character,
+                                    family: info.family.clone(),
+                                    page,
+                                });
+                            }
+                        }
+                    }
+                }
+                FrameItem::Shape(..)
+                | FrameItem::Image(..)
+                | FrameItem::Link(..)
+                | FrameItem::Tag(..) => {}
+            }
+        }
+    }
+}
+
+struct RequestedFamilies<'a> {
+    book: &'a FontBook,
+    /// Lowercase names of the requested families.
+    families: Vec<String>,
+}
+
+impl RequestedFamilies<'_> {
+    fn is_fallback(&self, family: &str) -> bool {
+        !self.families.is_empty() && !self.families.contains(&family.to_lowercase())
+    }
+
+    fn covers(&self, character: char) -> bool {
+        self.families.iter().any(|family| {
+            self.book.select_family(family).any(|index| {
+                self.book
+                    .info(index)
+                    .is_some_and(|info| info.coverage.contains(character as u32))
+            })
+        })
+    }
+}
+
+impl Display for FontReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Used fonts:")?;
+        for font in &self.used {
+            writeln!(
+                f,
+                "  {} ({:?}, weight {:?}, stretch {:?})",
+                font.family,
+                font.variant.style,
+                font.variant.weight,
+                font.variant.stretch.to_ratio()
+            )?;
+        }
+        if !self.fallback_glyphs.is_empty() {
+            writeln!(f, "Glyphs missing in the requested fonts:")?;
+            for fallback in &self.fallback_glyphs {
+                writeln!(
+                    f,
+                    "  '{}' (U+{:04X}) on page {}, rendered with {}",
+                    fallback.character, fallback.character as u32, fallback.page, fallback.family
+                )?;
+            }
+        }
+        if !self.missing_glyphs.is_empty() {
+            writeln!(f, "Missing glyphs:")?;
+            for missing in &self.missing_glyphs {
+                writeln!(
+                    f,
+                    "  '{}' (U+{:04X}) on page {}, rendered with {}",
+                    missing.character, missing.character as u32, missing.page, missing.family
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::font_report::FontReport;
+    use crate::manifest::OicanaWorldFiles;
+    use crate::world::OicanaWorld;
+    use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_input::TemplateInputs;
+    use std::collections::HashMap;
+    #[cfg(feature = "embedded-fonts")]
+    use typst::{foundations::Bytes, text::FontInfo};
+
+    #[test]
+    fn reports_used_fonts_and_missing_glyphs() {
+        let mut world = test_world(
+            r#"
+            #set text(font: "Libertinus Serif")
+            Hello *Ada*
+            #pagebreak()
+            #text(font: "DejaVu Sans Mono")[Missing: 𓀀]
+            "#,
+        );
+        world.report_fonts = true;
+        let compiled = world.compile().expect("Failed to compile");
+        let report = compiled.fonts.expect("Missing font report");
+
+        assert_eq!(
+            report,
+            FontReport::new(&compiled.document, &world.fonts().book, &[])
+        );
+        assert_eq!(
+            report
+                .used
+                .iter()
+                .map(|font| font.family.as_str())
+                .collect::<Vec<_>>(),
+            ["Libertinus Serif", "Libertinus Serif", "DejaVu Sans Mono"]
+        );
+        assert_eq!(report.missing_glyphs.len(), 1);
+        assert_eq!(report.missing_glyphs[0].character, '𓀀');
+        assert_eq!(report.missing_glyphs[0].page, 2);
+    }
+
+    #[test]
+    fn reports_glyphs_of_fallback_fonts() {
+        let mut world = test_world(
+            r#"
+            #set text(font: "Libertinus Serif")
+            Hello ─ Ada
+            "#,
+        );
+        world.report_fonts = true;
+        world.requested_font_families = vec!["Libertinus Serif".to_owned()];
+        let compiled = world.compile().expect("Failed to compile");
+        let report = compiled.fonts.expect("Missing font report");
+
+        assert!(report.missing_glyphs.is_empty());
+        assert_eq!(report.fallback_glyphs.len(), 1);
+        assert_eq!(report.fallback_glyphs[0].character, '─');
+        assert_eq!(report.fallback_glyphs[0].page, 1);
+        assert_ne!(report.fallback_glyphs[0].family, "Libertinus Serif");
+    }
+
+    #[test]
+    #[cfg(feature = "embedded-fonts")]
+    fn expects_template_fonts_by_default() {
+        let libertinus = typst_assets::fonts()
+            .find(|data| FontInfo::iter(data).any(|info| info.family == "Libertinus Serif"))
+            .expect("Missing embedded font");
+        let files = PreloadedTemplate::new(test_files(
+            r#"
+            #set text(font: "Libertinus Serif")
+            Hello ─ Ada
+            "#,
+        ))
+        .with_font("fonts/libertinus.otf", Bytes::new(libertinus));
+        let manifest = files.manifest().expect("should be able to parse manifest");
+        let mut world = OicanaWorld::new(files, TemplateInputs::new(), manifest)
+            .expect("Failed to create world");
+        world.report_fonts = true;
+        let compiled = world.compile().expect("Failed to compile");
+        let report = compiled.fonts.expect("Missing font report");
+
+        assert_eq!(report.fallback_glyphs.len(), 1);
+        assert_eq!(report.fallback_glyphs[0].character, '─');
+    }
+
+    fn test_world(main: &str) -> OicanaWorld<PreloadedTemplate> {
+        let files = PreloadedTemplate::new(test_files(main));
+        let manifest = files.manifest().expect("should be able to parse manifest");
+        OicanaWorld::new(files, TemplateInputs::new(), manifest).expect("Failed to create world")
+    }
+
+    fn test_files(main: &str) -> HashMap<String, String> {
+        let mut files = HashMap::new();
+        files.insert("main.typ".to_owned(), main.to_owned());
+        files.insert(
+            "typst.toml".to_owned(),
+            r#"
+        [package]
+        entrypoint = "main.typ"
+        name = "test"
+        version = "0.1.0"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#
+            .to_owned(),
+        );
+
+        files
+    }
+}
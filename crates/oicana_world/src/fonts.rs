@@ -1,15 +1,22 @@
-use std::fs;
-use std::path::PathBuf;
+#[cfg(feature = "native")]
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
+use log::warn;
 use oicana_files::TemplateFiles;
 use typst::foundations::Bytes;
-use typst::text::{Font, FontBook};
+use typst::syntax::FileId;
+use typst::text::{Font, FontBook, FontInfo};
+use typst::utils::LazyHash;
 
 /// Collects all fonts.
+///
+/// Fonts are loaded lazily from the template files they were collected from. A collection can
+/// be shared between worlds of the same template with [`crate::world::OicanaWorld::with_fonts`].
+#[derive(Debug)]
 pub struct FontCollection {
     /// Metadata about all discovered fonts.
-    pub book: FontBook,
+    pub book: LazyHash<FontBook>,
     /// Slots that the fonts are loaded into.
     pub fonts: Vec<FontSlot>,
 }
@@ -17,64 +24,266 @@ pub struct FontCollection {
 /// Holds details about the location of a font and lazily the font itself.
 #[derive(Debug)]
 pub struct FontSlot {
-    /// The path at which the font can be found on the system.
-    path: PathBuf,
-    /// The index of the font in its collection. Zero if the path does not point
-    /// to a collection.
+    /// Where the font data can be found.
+    source: FontSource,
+    /// The index of the font in its collection. Zero if the data is not a collection.
     index: u32,
     /// The lazily loaded font.
     font: OnceLock<Option<Font>>,
 }
 
+#[derive(Debug)]
+enum FontSource {
+    /// A font file of the template.
+    Template(FileId),
+    /// Font data registered at runtime.
+    Registered(Bytes),
+    /// A font embedded in Typst.
+    #[cfg(feature = "embedded-fonts")]
+    Embedded(&'static [u8]),
+    /// A font file in the file system.
+    #[cfg(feature = "native")]
+    System(PathBuf),
+}
+
 impl FontSlot {
     /// Get the font for this slot.
-    pub fn get(&self) -> Option<Font> {
+    ///
+    /// The font is read and parsed on first access.
+    pub fn get<Files: TemplateFiles>(&self, files: &Files) -> Option<Font> {
         self.font
             .get_or_init(|| {
-                let data = Bytes::new(fs::read(&self.path).ok()?);
+                let data = match self.source {
+                    FontSource::Template(id) => match files.file(id) {
+                        Ok(data) => data,
+                        Err(error) => {
+                            warn!("Failed to read font file {:?}: {error}", id.vpath());
+                            return None;
+                        }
+                    },
+                    FontSource::Registered(ref data) => data.clone(),
+                    #[cfg(feature = "embedded-fonts")]
+                    FontSource::Embedded(data) => Bytes::new(data),
+                    #[cfg(feature = "native")]
+                    FontSource::System(ref path) => match std::fs::read(path) {
+                        Ok(data) => Bytes::new(data),
+                        Err(error) => {
+                            warn!("Failed to read font file {}: {error}", path.display());
+                            return None;
+                        }
+                    },
+                };
                 Font::new(data, self.index)
             })
             .clone()
     }
 }
 
+impl Default for FontCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FontCollection {
     /// Create a new, empty font collection.
     pub fn new() -> Self {
         Self {
-            book: FontBook::new(),
+            book: LazyHash::new(FontBook::new()),
             fonts: vec![],
         }
     }
 
+    /// Families of the fonts collected from the template files, in order of collection.
+    pub fn template_families(&self) -> Vec<String> {
+        let mut families: Vec<String> = vec![];
+        for (index, slot) in self.fonts.iter().enumerate() {
+            if !matches!(slot.source, FontSource::Template(_)) {
+                continue;
+            }
+            if let Some(info) = self.book.info(index) {
+                if !families.contains(&info.family) {
+                    families.push(info.family.clone());
+                }
+            }
+        }
+
+        families
+    }
+
+    /// Collect the fonts of the template files and the fonts embedded in Typst.
+    ///
+    /// Embedded fonts are only available with the `embedded-fonts` feature.
     pub fn collect<Files: TemplateFiles>(&mut self, files: &Files) {
         // Fonts from the template have the highest priority
         self.add_template_fonts(files);
+        #[cfg(feature = "embedded-fonts")]
+        self.add_embedded_fonts();
+    }
+
+    /// Collect the fonts of the template files, fonts in the given directories, and optionally
+    /// the fonts installed on the system.
+    ///
+    /// Fonts embedded in Typst are added last, so they have the lowest priority.
+    #[cfg(feature = "native")]
+    pub fn collect_with_system_fonts<Files: TemplateFiles, P: AsRef<Path>>(
+        &mut self,
+        files: &Files,
+        font_paths: &[P],
+        include_system_fonts: bool,
+    ) {
+        self.add_template_fonts(files);
+        self.add_system_fonts(font_paths, include_system_fonts);
+        #[cfg(feature = "embedded-fonts")]
+        self.add_embedded_fonts();
+    }
+
+    /// Collect the fonts of the template files and fonts registered at runtime.
+    ///
+    /// Registered fonts are added after the template fonts and before the fonts embedded in Typst.
+    pub fn collect_with_registered<Files: TemplateFiles>(
+        &mut self,
+        files: &Files,
+        registered: &[Bytes],
+    ) {
+        self.add_template_fonts(files);
+        for data in registered {
+            self.add_fonts(data, || FontSource::Registered(data.clone()));
+        }
+        #[cfg(feature = "embedded-fonts")]
         self.add_embedded_fonts();
     }
 
     fn add_template_fonts<Files: TemplateFiles>(&mut self, files: &Files) {
         for file_id in files.font_files() {
-            let data = files.file(*file_id).expect("Failed to read font file");
-            self.load_fonts_from_bytes(data);
+            // The font is loaded again on first use. Until then, only its metadata is needed.
+            let data = match files.file_uncached(*file_id) {
+                Ok(data) => data,
+                Err(error) => {
+                    warn!("Failed to read font file {:?}: {error}", file_id.vpath());
+                    continue;
+                }
+            };
+            self.add_fonts(&data, || FontSource::Template(*file_id));
         }
     }
 
+    #[cfg(feature = "native")]
+    fn add_system_fonts<P: AsRef<Path>>(&mut self, font_paths: &[P], include_system_fonts: bool) {
+        let fonts = typst_kit::fonts::Fonts::searcher()
+            .include_system_fonts(include_system_fonts)
+            .search_with(font_paths);
+        for (i, slot) in fonts.fonts.iter().enumerate() {
+            let (Some(info), Some(path)) = (fonts.book.info(i), slot.path()) else {
+                continue;
+            };
+            self.book.push(info.clone());
+            self.fonts.push(FontSlot {
+                source: FontSource::System(path.to_path_buf()),
+                index: slot.index(),
+                font: OnceLock::new(),
+            });
+        }
+    }
+
+    #[cfg(feature = "embedded-fonts")]
     fn add_embedded_fonts(&mut self) {
         for data in typst_assets::fonts() {
-            let buffer = Bytes::new(data);
-            self.load_fonts_from_bytes(buffer);
+            self.add_fonts(data, || FontSource::Embedded(data));
         }
     }
 
-    fn load_fonts_from_bytes(&mut self, data: Bytes) {
-        for (i, font) in Font::iter(data).enumerate() {
-            self.book.push(font.info().clone());
+    /// Only the metadata of the fonts is parsed. The fonts are loaded by their slots on first use.
+    fn add_fonts(&mut self, data: &[u8], source: impl Fn() -> FontSource) {
+        for (i, info) in FontInfo::iter(data).enumerate() {
+            self.book.push(info);
             self.fonts.push(FontSlot {
-                path: PathBuf::new(),
+                source: source(),
                 index: i as u32,
-                font: OnceLock::from(Some(font)),
+                font: OnceLock::new(),
             });
         }
     }
 }
+
+/// Get the family names of all fonts in the given font file or collection.
+///
+/// The result is empty if the data does not contain a font that Typst can read.
+pub fn font_families(data: &[u8]) -> Vec<String> {
+    FontInfo::iter(data).map(|info| info.family).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fonts::FontCollection;
+    use oicana_files::packed::{LazyPackedTemplate, PackedTemplate};
+    use oicana_files::TemplateFiles;
+    use std::fs::read;
+    use std::io::Cursor;
+    use typst::diag::{FileError, FileResult};
+    use typst::foundations::Bytes;
+    use typst::syntax::{FileId, Source, VirtualPath};
+
+    /// Template files with a font that cannot be read.
+    struct UnreadableFont {
+        fonts: Vec<FileId>,
+    }
+
+    impl TemplateFiles for UnreadableFont {
+        fn source(&self, id: FileId) -> FileResult<Source> {
+            Err(FileError::NotFound(id.vpath().as_rootless_path().into()))
+        }
+
+        fn file(&self, _id: FileId) -> FileResult<Bytes> {
+            Err(FileError::InvalidUtf8)
+        }
+
+        fn font_files(&self) -> &Vec<FileId> {
+            &self.fonts
+        }
+    }
+
+    #[test]
+    fn loads_fonts_on_first_use() {
+        let template =
+            read("../../assets/templates/fonts-0.1.0.zip").expect("Failed to read template zip");
+        let files = PackedTemplate::new(Cursor::new(template));
+        let mut fonts = FontCollection::new();
+        fonts.collect(&files);
+
+        assert!(fonts.fonts.iter().all(|slot| slot.font.get().is_none()));
+        let info = fonts.book.info(0).expect("Missing template font").clone();
+        let font = fonts.fonts[0].get(&files).expect("Failed to load font");
+        assert_eq!(font.info(), &info);
+        assert!(fonts.fonts[0].font.get().is_some());
+        assert!(fonts.fonts[1..]
+            .iter()
+            .all(|slot| slot.font.get().is_none()));
+    }
+
+    #[test]
+    fn does_not_cache_indexed_template_fonts() {
+        let template =
+            read("../../assets/templates/fonts-0.1.0.zip").expect("Failed to read template zip");
+        let files = LazyPackedTemplate::try_new(Cursor::new(template), usize::MAX)
+            .expect("Failed to read template");
+        let mut fonts = FontCollection::new();
+        fonts.collect(&files);
+
+        assert!(fonts.book.info(0).is_some());
+        assert_eq!(files.cached_bytes(), 0);
+    }
+
+    #[test]
+    fn skips_unreadable_template_fonts() {
+        let files = UnreadableFont {
+            fonts: vec![FileId::new(None, VirtualPath::new("broken.ttf"))],
+        };
+        let mut fonts = FontCollection::new();
+        fonts.collect(&files);
+
+        let mut without_template_fonts = FontCollection::new();
+        without_template_fonts.collect(&UnreadableFont { fonts: vec![] });
+        assert_eq!(fonts.fonts.len(), without_template_fonts.fonts.len());
+    }
+}
@@ -5,11 +5,18 @@ use std::fmt::Display;
 use thiserror::Error;
 use typst::layout::PagedDocument;
 
+use crate::font_report::FontReport;
+
 /// Diagnostics for the Typst World with codespan-reporting
 pub mod diagnostics;
-mod fonts;
+/// Report fonts used in a compiled document
+pub mod font_report;
+/// Fonts available to the Typst World
+pub mod fonts;
 /// Get the manifest of an Oicana World
 pub mod manifest;
+/// Share template files and fonts between worlds of the same template
+pub mod shared;
 /// Oicana implementation of a Typst World
 pub mod world;
 
@@ -19,6 +26,8 @@ pub struct CompiledDocument {
     pub document: PagedDocument,
     /// warnings from the compilation.
     pub warnings: Option<String>,
+    /// Fonts used in the document, if [`world::OicanaWorld::report_fonts`] is enabled.
+    pub fonts: Option<FontReport>,
 }
 
 /// Error while compiling a template.
@@ -51,3 +60,18 @@ pub fn get_current_time() -> f64 {
         chrono::Utc::now().timestamp_millis() as f64
     }
 }
+
+/// Number of documents a batch compiles before evicting the compilation cache.
+///
+/// Batches are processed in chunks of this size to bound the memory of cached compilation
+/// results and of exported documents that were not handed out yet.
+pub const BATCH_CHUNK_SIZE: usize = 16;
+
+/// Evict all entries from Typst's global compilation cache.
+///
+/// [`world::OicanaWorld::compile`] does this before every compilation.
+/// Call this after compiling documents with [`world::OicanaWorld::compile_with_inputs`],
+/// at least every [`BATCH_CHUNK_SIZE`] documents.
+pub fn evict_cache() {
+    comemo::evict(0);
+}
@@ -0,0 +1,193 @@
+use crate::fonts::{font_families, FontCollection};
+use log::debug;
+use oicana_files::packed::{PackedTemplate, PackedTemplateError};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex, Weak};
+use typst::foundations::Bytes;
+
+/// Files and fonts of a packed template that can be shared between worlds.
+#[derive(Clone)]
+pub struct SharedTemplate {
+    /// The decompressed template files.
+    pub files: Arc<PackedTemplate>,
+    /// Fonts of the template, registered fonts, and the fonts embedded in Typst.
+    pub fonts: Arc<FontCollection>,
+}
+
+/// Packed templates keyed by the SHA-256 hash of their archive.
+///
+/// Loading an archive that is still in use by another world returns the same files and fonts
+/// instead of decompressing and parsing them again. Templates are only held weakly, they are
+/// dropped together with the last world using them.
+///
+/// Fonts registered with [`Self::register_font`] are available to all templates loaded afterwards.
+#[derive(Default)]
+pub struct SharedTemplates {
+    templates: Mutex<HashMap<[u8; 32], WeakTemplate>>,
+    registered_fonts: Mutex<Vec<Bytes>>,
+}
+
+struct WeakTemplate {
+    files: Weak<PackedTemplate>,
+    /// Empty after a font was registered, until the fonts are collected again.
+    fonts: Weak<FontCollection>,
+}
+
+impl SharedTemplates {
+    /// Create an empty set of shared templates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the files and fonts of the given template archive.
+    pub fn load(&self, archive: &[u8]) -> Result<SharedTemplate, PackedTemplateError> {
+        // A cryptographic hash, so that one archive can never be served for a different one
+        let hash = Sha256::digest(archive);
+        let mut templates = self.templates.lock().unwrap();
+        if let Some(template) = templates.get_mut(hash.as_slice()) {
+            if let Some(files) = template.files.upgrade() {
+                debug!("Reusing shared template {hash:x}");
+                let fonts = self.template_fonts(template, &files);
+                return Ok(SharedTemplate { files, fonts });
+            }
+        }
+
+        let files = Arc::new(PackedTemplate::try_new(Cursor::new(archive))?);
+        let mut template = WeakTemplate {
+            files: Arc::downgrade(&files),
+            fonts: Weak::new(),
+        };
+        let fonts = self.template_fonts(&mut template, &files);
+
+        templates.retain(|_, template| template.files.strong_count() > 0);
+        templates.insert(hash.into(), template);
+
+        Ok(SharedTemplate { files, fonts })
+    }
+
+    /// Get the current fonts for files of a loaded template.
+    ///
+    /// After registering a font, use this to update the fonts of existing worlds.
+    pub fn fonts(&self, files: &Arc<PackedTemplate>) -> Arc<FontCollection> {
+        let mut templates = self.templates.lock().unwrap();
+        match templates.values_mut().find(|template| {
+            template
+                .files
+                .upgrade()
+                .is_some_and(|shared| Arc::ptr_eq(&shared, files))
+        }) {
+            Some(template) => self.template_fonts(template, files),
+            None => Arc::new(self.collect_fonts(files)),
+        }
+    }
+
+    /// Register font data for all templates.
+    ///
+    /// Returns the families of the fonts in the data. Nothing is registered if the data does not
+    /// contain any font. Worlds that were created before keep their fonts until they are updated
+    /// with [`Self::fonts`].
+    pub fn register_font(&self, data: Bytes) -> Vec<String> {
+        let families = font_families(&data);
+        if families.is_empty() {
+            return families;
+        }
+
+        let mut templates = self.templates.lock().unwrap();
+        self.registered_fonts.lock().unwrap().push(data);
+        for template in templates.values_mut() {
+            template.fonts = Weak::new();
+        }
+
+        families
+    }
+
+    fn template_fonts(
+        &self,
+        template: &mut WeakTemplate,
+        files: &Arc<PackedTemplate>,
+    ) -> Arc<FontCollection> {
+        if let Some(fonts) = template.fonts.upgrade() {
+            return fonts;
+        }
+        let fonts = Arc::new(self.collect_fonts(files));
+        template.fonts = Arc::downgrade(&fonts);
+
+        fonts
+    }
+
+    fn collect_fonts(&self, files: &Arc<PackedTemplate>) -> FontCollection {
+        let mut fonts = FontCollection::new();
+        fonts.collect_with_registered(files, &self.registered_fonts.lock().unwrap());
+
+        fonts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::SharedTemplates;
+    use oicana_files::packed::PackedTemplate;
+    use oicana_files::TemplateFiles;
+    use std::fs::read;
+    use std::io::Cursor;
+    use std::sync::Arc;
+    use typst::foundations::Bytes;
+
+    #[test]
+    fn reuses_identical_templates() {
+        let archive =
+            read("../../assets/templates/test-0.1.0.zip").expect("Failed to read template zip");
+        let templates = SharedTemplates::new();
+
+        let first = templates.load(&archive).expect("Failed to load template");
+        let second = templates.load(&archive).expect("Failed to load template");
+        assert!(Arc::ptr_eq(&first.files, &second.files));
+        assert!(Arc::ptr_eq(&first.fonts, &second.fonts));
+        let other = read("../../assets/templates/fonts-0.1.0.zip")
+            .map(|archive| templates.load(&archive))
+            .expect("Failed to read template zip")
+            .expect("Failed to load template");
+        assert!(!Arc::ptr_eq(&first.files, &other.files));
+        drop(other);
+
+        let files = Arc::downgrade(&first.files);
+        drop(first);
+        drop(second);
+        assert!(files.upgrade().is_none());
+        templates.load(&archive).expect("Failed to load template");
+        assert_eq!(templates.templates.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn registered_fonts_replace_shared_fonts() {
+        let archive =
+            read("../../assets/templates/test-0.1.0.zip").expect("Failed to read template zip");
+        let font = read("../../assets/templates/fonts-0.1.0.zip")
+            .map(|fonts| PackedTemplate::new(Cursor::new(fonts)))
+            .expect("Failed to read font template");
+        let font = font
+            .file(font.font_files()[1])
+            .expect("Failed to read font");
+        let templates = SharedTemplates::new();
+
+        let template = templates.load(&archive).expect("Failed to load template");
+        assert!(templates
+            .register_font(Bytes::new(b"no font".to_vec()))
+            .is_empty());
+        assert_eq!(templates.register_font(font), ["Inria Serif"]);
+
+        let fonts = templates.fonts(&template.files);
+        assert!(!Arc::ptr_eq(&fonts, &template.fonts));
+        assert_eq!(fonts.fonts.len(), template.fonts.fonts.len() + 1);
+        assert!(fonts.book.contains_family("inria serif"));
+        assert!(Arc::ptr_eq(
+            &fonts,
+            &templates
+                .load(&archive)
+                .expect("Failed to load template")
+                .fonts
+        ));
+    }
+}
@@ -1,14 +1,17 @@
 use crate::diagnostics::{DiagnosticColor, TemplateDiagnostics};
-use crate::fonts::{FontCollection, FontSlot};
-use crate::{get_current_time, CompiledDocument, TemplateCompilationFailure};
+use crate::font_report::FontReport;
+use crate::fonts::FontCollection;
+use crate::{evict_cache, get_current_time, CompiledDocument, TemplateCompilationFailure};
 
 use chrono::{DateTime, Datelike, Local};
 use log::info;
+use oicana_files::packed::file_id;
 use oicana_files::TemplateFiles;
 use oicana_input::TemplateInputs;
+use oicana_template::build::{file_hash, BuildInfo, IncompatibleTemplate, BUILD_INFO_FILE};
 use oicana_template::manifest::ManifestValidationError;
 use oicana_template::manifest::TemplateManifest;
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 use thiserror::Error;
 use typst::diag::{FileError, FileResult, Warned};
 use typst::foundations::{Bytes, Datetime};
@@ -18,16 +21,23 @@ use typst::utils::LazyHash;
 use typst::{Library, World};
 
 /// A world that provides access to fonts and template files.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct OicanaWorld<Files: TemplateFiles> {
     main: FileId,
     library: LazyHash<Library>,
-    book: LazyHash<FontBook>,
-    fonts: Vec<FontSlot>,
+    fonts: Arc<FontCollection>,
     now: OnceLock<DateTime<Local>>,
     manifest: TemplateManifest,
     /// Color mode for diagnostic logs
     pub color: DiagnosticColor,
+    /// Add a [`FontReport`] to every compiled document
+    pub report_fonts: bool,
+    /// Font families the document is expected to be set in
+    ///
+    /// Characters rendered with a font outside of these families are reported as
+    /// [`FontReport::fallback_glyphs`]. If empty, the families of the fonts in the template are
+    /// expected.
+    pub requested_font_families: Vec<String>,
     /// Files access to the template.
     pub files: Files,
 }
@@ -40,24 +50,40 @@ impl<Files: TemplateFiles> OicanaWorld<Files> {
         files: Files,
         inputs: TemplateInputs,
         manifest: TemplateManifest,
+    ) -> Result<Self, WorldCreationError> {
+        let mut fonts = FontCollection::new();
+        fonts.collect(&files);
+
+        Self::with_fonts(files, inputs, manifest, Arc::new(fonts))
+    }
+
+    /// Create a new Typst World with already collected fonts.
+    ///
+    /// Worlds of the same template can share their fonts instead of parsing them again.
+    /// The fonts must have been collected from the same template files.
+    /// See [`crate::shared::SharedTemplates`].
+    pub fn with_fonts(
+        files: Files,
+        inputs: TemplateInputs,
+        manifest: TemplateManifest,
+        fonts: Arc<FontCollection>,
     ) -> Result<Self, WorldCreationError> {
         let library = Library::builder().with_inputs(inputs.to_dict()).build();
 
         let main_path = VirtualPath::new(manifest.package.entrypoint.as_str());
         let main = FileId::new(None, main_path);
         files.source(main)?;
-
-        let mut searcher = FontCollection::new();
-        searcher.collect(&files);
+        check_build_info(&files)?;
 
         Ok(Self {
             main,
             library: LazyHash::new(library),
-            book: LazyHash::new(searcher.book),
-            fonts: searcher.fonts,
+            fonts,
             now: OnceLock::new(),
             manifest,
             color: DiagnosticColor::Ansi,
+            report_fonts: false,
+            requested_font_families: Vec::new(),
             files,
         })
     }
@@ -71,11 +97,35 @@ impl<Files: TemplateFiles> OicanaWorld<Files> {
 
     /// Compile the template world.
     pub fn compile(&mut self) -> Result<CompiledDocument, TemplateCompilationFailure> {
-        let start = get_current_time();
         // We take a small performance hit here
         // to prevent https://github.com/typst/typst/issues/6832
-        comemo::evict(0);
-        let Warned { output, warnings } = typst::compile(self);
+        evict_cache();
+        self.compile_world(self)
+    }
+
+    /// Compile the template with the given inputs without changing the inputs of the World.
+    ///
+    /// In contrast to [`Self::compile`], this only requires shared access to the World.
+    /// Multiple documents can be compiled from the same World in parallel.
+    /// The compilation cache is not evicted. Call [`evict_cache`] after every chunk of
+    /// [`crate::BATCH_CHUNK_SIZE`] compilations.
+    pub fn compile_with_inputs(
+        &self,
+        inputs: TemplateInputs,
+    ) -> Result<CompiledDocument, TemplateCompilationFailure> {
+        let world = InputsWorld {
+            world: self,
+            library: LazyHash::new(Library::builder().with_inputs(inputs.to_dict()).build()),
+        };
+        self.compile_world(&world)
+    }
+
+    fn compile_world(
+        &self,
+        world: &dyn World,
+    ) -> Result<CompiledDocument, TemplateCompilationFailure> {
+        let start = get_current_time();
+        let Warned { output, warnings } = typst::compile(world);
         info!("Compiled Document in {}ms", get_current_time() - start);
         let warnings = if warnings.is_empty() {
             None
@@ -84,7 +134,20 @@ impl<Files: TemplateFiles> OicanaWorld<Files> {
         };
 
         match output {
-            Ok(document) => Ok(CompiledDocument { document, warnings }),
+            Ok(document) => Ok(CompiledDocument {
+                fonts: self.report_fonts.then(|| {
+                    let template_families;
+                    let families = if self.requested_font_families.is_empty() {
+                        template_families = self.fonts.template_families();
+                        &template_families
+                    } else {
+                        &self.requested_font_families
+                    };
+                    FontReport::new(&document, &self.fonts.book, families)
+                }),
+                document,
+                warnings,
+            }),
             Err(diagnostics) => Err(TemplateCompilationFailure {
                 error: String::from_utf8_lossy(&self.format_diagnostics(diagnostics)).into(),
                 warnings,
@@ -92,12 +155,48 @@ impl<Files: TemplateFiles> OicanaWorld<Files> {
         }
     }
 
+    /// Fonts available to the template
+    pub fn fonts(&self) -> &Arc<FontCollection> {
+        &self.fonts
+    }
+
+    /// Replace the fonts available to the template
+    ///
+    /// The fonts must have been collected from the same template files.
+    pub fn set_fonts(&mut self, fonts: Arc<FontCollection>) {
+        self.fonts = fonts;
+    }
+
     /// Manifest of the Oicana template
     pub fn manifest(&self) -> &TemplateManifest {
         &self.manifest
     }
 }
 
+/// Check that the template was packed by a compatible version of Oicana and that the packed files
+/// were not modified afterwards.
+///
+/// Templates without build metadata, like native templates, are not checked.
+fn check_build_info(files: &impl TemplateFiles) -> Result<(), WorldCreationError> {
+    let build_info = match files.file(FileId::new(None, VirtualPath::new(BUILD_INFO_FILE))) {
+        Ok(build_info) => build_info,
+        Err(FileError::NotFound(_)) => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+    let build_info = BuildInfo::from_json(&String::from_utf8_lossy(&build_info))
+        .map_err(WorldCreationError::InvalidBuildInfo)?;
+    build_info.check_compatibility()?;
+    for (path, hash) in &build_info.files {
+        let id = file_id(path).map_err(|_| WorldCreationError::ModifiedFile(path.clone()))?;
+        // Files are only hashed once, there is no need to keep them in a cache.
+        if file_hash(&files.file_uncached(id)?) != *hash {
+            return Err(WorldCreationError::ModifiedFile(path.clone()));
+        }
+    }
+
+    Ok(())
+}
+
 /// An error that occurs during world construction.
 #[derive(Error, Debug)]
 pub enum WorldCreationError {
@@ -107,6 +206,15 @@ pub enum WorldCreationError {
     /// Error in the template manifest
     #[error("There was an issue with the package manifest")]
     ManifestError(#[from] ManifestValidationError),
+    /// The template was packed by an incompatible version of Oicana
+    #[error("{0}")]
+    Incompatible(#[from] IncompatibleTemplate),
+    /// The build metadata of the template cannot be parsed
+    #[error("Failed to parse the build metadata of the template: {0}")]
+    InvalidBuildInfo(#[source] serde_json::Error),
+    /// A packed file does not match its hash in the build metadata
+    #[error("The file '{0}' was modified after the template was packed")]
+    ModifiedFile(String),
 }
 
 impl<Files: TemplateFiles> World for OicanaWorld<Files> {
@@ -115,7 +223,7 @@ impl<Files: TemplateFiles> World for OicanaWorld<Files> {
     }
 
     fn book(&self) -> &LazyHash<FontBook> {
-        &self.book
+        &self.fonts.book
     }
 
     fn main(&self) -> FileId {
@@ -131,7 +239,7 @@ impl<Files: TemplateFiles> World for OicanaWorld<Files> {
     }
 
     fn font(&self, index: usize) -> Option<Font> {
-        self.fonts[index].get()
+        self.fonts.fonts[index].get(&self.files)
     }
 
     fn today(&self, offset: Option<i64>) -> Option<Datetime> {
@@ -150,13 +258,51 @@ impl<Files: TemplateFiles> World for OicanaWorld<Files> {
     }
 }
 
+/// A World that shares everything but the inputs with another World.
+struct InputsWorld<'a, Files: TemplateFiles> {
+    world: &'a OicanaWorld<Files>,
+    library: LazyHash<Library>,
+}
+
+impl<Files: TemplateFiles> World for InputsWorld<'_, Files> {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.world.book()
+    }
+
+    fn main(&self) -> FileId {
+        self.world.main()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        self.world.source(id)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.world.file(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.world.font(index)
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        self.world.today(offset)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::manifest::{OicanaWorldFiles, OicanaWorldManifestError};
     use crate::world::{OicanaWorld, WorldCreationError};
     use oicana_files::preloaded::PreloadedTemplate;
+    use oicana_input::input::json::JsonInput;
     use oicana_input::TemplateInputs;
-    use std::collections::HashMap;
+    use oicana_template::build::{file_hash, BuildInfo, IncompatibleTemplate, BUILD_INFO_FILE};
+    use std::collections::{BTreeMap, HashMap};
     use std::path::PathBuf;
     use typst::diag::FileError;
 
@@ -254,4 +400,120 @@ mod tests {
             FileError::NotFound(PathBuf::from("/not_main.typ"))
         )
     }
+
+    #[test]
+    fn fails_to_build_world_for_templates_packed_with_newer_typst() {
+        let mut files = HashMap::new();
+        files.insert(
+            "typst.toml".to_owned(),
+            r#"
+        [package]
+        entrypoint = "main.typ"
+        name = "test"
+        version = "0.1.0"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#
+            .to_owned(),
+        );
+        files.insert("main.typ".to_owned(), "Test".to_owned());
+        let build_info = BuildInfo {
+            typst_version: "99.0.0".to_owned(),
+            ..BuildInfo::current(1, None, BTreeMap::new())
+        };
+        files.insert(BUILD_INFO_FILE.to_owned(), build_info.to_json());
+        let files = PreloadedTemplate::new(files);
+        let manifest = files.manifest().expect("should be able to parse manifest");
+
+        let Err(WorldCreationError::Incompatible(IncompatibleTemplate::NewerTypst {
+            template,
+            ..
+        })) = OicanaWorld::new(files, TemplateInputs::new(), manifest)
+        else {
+            panic!("Created a world for an incompatible template or got unexpected error")
+        };
+
+        assert_eq!(template, "99.0.0")
+    }
+
+    #[test]
+    fn fails_to_build_world_for_files_modified_after_packing() {
+        let mut files = HashMap::new();
+        files.insert(
+            "typst.toml".to_owned(),
+            r#"
+        [package]
+        entrypoint = "main.typ"
+        name = "test"
+        version = "0.1.0"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#
+            .to_owned(),
+        );
+        files.insert("main.typ".to_owned(), "Modified".to_owned());
+        let hashes = BTreeMap::from([
+            ("main.typ".to_owned(), file_hash(b"Test")),
+            (
+                "typst.toml".to_owned(),
+                file_hash(files["typst.toml"].as_bytes()),
+            ),
+        ]);
+        files.insert(
+            BUILD_INFO_FILE.to_owned(),
+            BuildInfo::current(1, None, hashes).to_json(),
+        );
+        let files = PreloadedTemplate::new(files);
+        let manifest = files.manifest().expect("should be able to parse manifest");
+
+        let Err(WorldCreationError::ModifiedFile(path)) =
+            OicanaWorld::new(files, TemplateInputs::new(), manifest)
+        else {
+            panic!("Created a world for a modified template or got unexpected error")
+        };
+
+        assert_eq!(path, "main.typ")
+    }
+
+    #[test]
+    fn compiles_with_inputs_without_changing_world() {
+        let mut files = HashMap::new();
+        files.insert(
+            "main.typ".to_owned(),
+            r#"
+            #let pages = int(sys.inputs.at("oicana-inputs").at("pages", default: "1"))
+            #for _ in range(pages - 1) { pagebreak() }
+            "#
+            .to_owned(),
+        );
+        files.insert(
+            "typst.toml".to_owned(),
+            r#"
+        [package]
+        entrypoint = "main.typ"
+        name = "test"
+        version = "0.1.0"
+
+        [tool.oicana]
+        manifest_version = 1
+        "#
+            .to_owned(),
+        );
+        let files = PreloadedTemplate::new(files);
+        let manifest = files.manifest().expect("should be able to parse manifest");
+        let mut world = OicanaWorld::new(files, TemplateInputs::new(), manifest)
+            .expect("Failed to create world");
+        let mut inputs = TemplateInputs::new();
+        inputs.with_input(JsonInput::new("pages", "3"));
+
+        let with_inputs = world
+            .compile_with_inputs(inputs)
+            .expect("Failed to compile with inputs");
+        let without_inputs = world.compile().expect("Failed to compile");
+
+        assert_eq!(with_inputs.document.pages.len(), 3);
+        assert_eq!(without_inputs.document.pages.len(), 1);
+    }
 }
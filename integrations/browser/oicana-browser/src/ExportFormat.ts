@@ -1,5 +1,53 @@
-export type ExportFormat = { format: "pdf" | "svg" } | { format: "png", pixelsPerPt: number };
+export type ExportFormat = { format: "pdf", encryption?: PdfEncryption }
+  | { format: "svg", pages?: PageRange }
+  | { format: "png", pixelsPerPt: number, pages?: PageRange }
+  | { format: "jpeg", pixelsPerPt: number, quality: number, pages?: PageRange }
+  | { format: "webp", pixelsPerPt: number, pages?: PageRange }
+  | { format: "thumbnail", maxWidth: number, maxHeight: number, quality: number }
+  | { format: "text" };
+
+/**
+ * One-indexed, inclusive range of pages to export as image
+ *
+ * A missing bound leaves the range open on that side. Only the selected pages are rendered.
+ */
+export type PageRange = {
+  first?: number,
+  last?: number,
+};
+
+/**
+ * Password protection for PDF exports
+ *
+ * Encrypted PDFs are not PDF/A conform, because the standard does not allow encryption.
+ */
+export type PdfEncryption = {
+  /** Password required to open the document. Leave empty to only restrict permissions. */
+  userPassword?: string,
+  /** Password granting full access to the document. Must not be empty. */
+  ownerPassword: string,
+  /** What users opening the document with the user password are allowed to do. Everything is allowed by default. */
+  permissions?: { print?: boolean, copy?: boolean, modify?: boolean },
+};
 
 export const Pdf: ExportFormat = { format: "pdf" };
 export const Svg: ExportFormat = { format: "svg" };
-export const Png = (pixelsPerPt: number): ExportFormat => ({ format: "png", pixelsPerPt })
+export const Png = (pixelsPerPt: number, pages?: PageRange): ExportFormat => ({ format: "png", pixelsPerPt, pages })
+/**
+ * Lossy JPEG image with a quality between 1 and 100
+ */
+export const Jpeg = (pixelsPerPt: number, quality: number = 85, pages?: PageRange): ExportFormat => ({ format: "jpeg", pixelsPerPt, quality, pages })
+/**
+ * Lossless WebP image without a quality setting
+ */
+export const WebP = (pixelsPerPt: number, pages?: PageRange): ExportFormat => ({ format: "webp", pixelsPerPt, pages })
+/**
+ * JPEG preview of the first page, fitted into the given bounding box in pixels
+ */
+export const Thumbnail = (maxWidth: number, maxHeight: number, quality: number = 85): ExportFormat => ({ format: "thumbnail", maxWidth, maxHeight, quality })
+/**
+ * UTF-8 encoded plain text of all pages, separated by form feeds. Decode it with a `TextDecoder`.
+ */
+export const Text: ExportFormat = { format: "text" };
+export const SvgPages = (pages: PageRange): ExportFormat => ({ format: "svg", pages })
+export const EncryptedPdf = (encryption: PdfEncryption): ExportFormat => ({ format: "pdf", encryption })
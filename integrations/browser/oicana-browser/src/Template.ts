@@ -1,4 +1,4 @@
-import {inputs as wasmInputs, register_template, compile_template, get_source, get_file} from "@oicana/browser-wasm";
+import {inputs as wasmInputs, register_template, compile_template, compile_template_formats, compile_template_batch, get_source, get_file} from "@oicana/browser-wasm";
 import {BlobInputDefinition, BlobWithMetadata, JsonInputDefinition} from "./inputs";
 import {ExportFormat} from "./ExportFormat";
 import { CompilationMode } from "./CompilationMode";
@@ -85,6 +85,53 @@ export class Template {
         return compile_template(this.template, jsonInputs ?? new Map(), blobInputs ?? new Map(), this.convertExportFormat(exportFormat), compilationMode ?? this.defaultCompilationMode);
     }
 
+    /**
+     * Compile the template once and export it into several formats
+     *
+     * For example, a PDF for download together with a PNG preview of the first page.
+     * @param jsonInputs
+     * @param blobInputs
+     * @param exportFormats
+     * @param compilationMode
+     * @returns one output per export format in the same order
+     */
+    public compileToFormats(jsonInputs: Map<string, string>, blobInputs: Map<string, BlobWithMetadata>, exportFormats: ExportFormat[], compilationMode?: CompilationMode): Uint8Array[] {
+      for (const blob of blobInputs.entries()) {
+        if(blob[1].meta === undefined) {
+          // Otherwise the FFI layer will fail to pass the blobs over to WASM
+          blob[1].meta = {}
+        }
+      }
+      return compile_template_formats(this.template, jsonInputs, blobInputs, exportFormats.map((format) => this.convertExportFormat(format)), compilationMode ?? this.defaultCompilationMode);
+    }
+
+    /**
+     * Compile the template once for every set of inputs
+     *
+     * A failing input set does not affect the others.
+     * @param inputSets
+     * @param exportFormat
+     * @param compilationMode
+     * @returns one result per input set in the same order
+     */
+    public compileBatch(inputSets: InputSet[], exportFormat?: ExportFormat, compilationMode?: CompilationMode): BatchResult[] {
+      for (const inputSet of inputSets) {
+        for (const blob of inputSet.blobInputs?.entries() ?? []) {
+          if(blob[1].meta === undefined) {
+            // Otherwise the FFI layer will fail to pass the blobs over to WASM
+            blob[1].meta = {}
+          }
+        }
+      }
+      return compile_template_batch(
+        this.template,
+        inputSets.map((inputSet) => inputSet.jsonInputs ?? new Map()),
+        inputSets.map((inputSet) => inputSet.blobInputs ?? new Map()),
+        this.convertExportFormat(exportFormat),
+        compilationMode ?? this.defaultCompilationMode
+      );
+    }
+
     /**
      * Gather all input definitions of this template
      */
@@ -127,7 +174,28 @@ export class Template {
       // eslint-disable-next-line @typescript-eslint/no-explicit-any
       let exportFormatInner: any;
       if (exportFormat.format === "png") {
-          exportFormatInner = {format: "png", pixels_per_pt: exportFormat.pixelsPerPt}
+          exportFormatInner = {format: "png", pixels_per_pt: exportFormat.pixelsPerPt, pages: exportFormat.pages}
+      } else if (exportFormat.format === "jpeg") {
+          exportFormatInner = {format: "jpeg", pixels_per_pt: exportFormat.pixelsPerPt, quality: exportFormat.quality, pages: exportFormat.pages}
+      } else if (exportFormat.format === "webp") {
+          if ("quality" in exportFormat) {
+            throw new Error("WebP images are lossless and have no quality setting");
+          }
+          exportFormatInner = {format: "webp", pixels_per_pt: exportFormat.pixelsPerPt, pages: exportFormat.pages}
+      } else if (exportFormat.format === "thumbnail") {
+          exportFormatInner = {format: "thumbnail", max_width: exportFormat.maxWidth, max_height: exportFormat.maxHeight, quality: exportFormat.quality}
+      } else if (exportFormat.format === "svg") {
+          exportFormatInner = {format: "svg", pages: exportFormat.pages}
+      } else if (exportFormat.format === "pdf" && exportFormat.encryption !== undefined) {
+          const encryption = exportFormat.encryption;
+          exportFormatInner = {
+            format: "pdf",
+            encryption: {
+              user_password: encryption.userPassword ?? "",
+              owner_password: encryption.ownerPassword,
+              permissions: encryption.permissions ?? {},
+            }
+          }
       } else {
           exportFormatInner = {format: exportFormat.format}
       }
@@ -135,3 +203,18 @@ export class Template {
       return exportFormatInner;
     }
 }
+
+/**
+ * Inputs for one compilation of a batch
+ */
+export interface InputSet {
+    jsonInputs?: Map<string, string>;
+    blobInputs?: Map<string, BlobWithMetadata>;
+}
+
+/**
+ * Result of compiling one input set of a batch
+ *
+ * Either the exported document or the error of this input set.
+ */
+export type BatchResult = { output: Uint8Array } | { error: string };
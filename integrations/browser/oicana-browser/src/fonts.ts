@@ -0,0 +1,13 @@
+import {register_font} from "@oicana/browser-wasm";
+
+/**
+ * Register a font for all templates
+ *
+ * Already registered templates and templates registered afterward can use the font.
+ * Fonts of a template take precedence over registered fonts.
+ * @param font - data of a font file or font collection, e.g. a TTF or OTF file
+ * @returns the families of the registered fonts
+ */
+export function registerFont(font: Uint8Array): string[] {
+    return register_font(font);
+}
@@ -2,6 +2,8 @@ import init from "@oicana/browser-wasm";
 
 export * from "./Template"
 export * from "./inputs"
+export * from "./fonts"
+export * from "./signatures"
 
 const initialized: Set<string> = new Set();
 
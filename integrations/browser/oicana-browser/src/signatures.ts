@@ -0,0 +1,15 @@
+import {require_signed_templates} from "@oicana/browser-wasm";
+
+/**
+ * Only accept templates signed by one of the given public keys
+ *
+ * Afterward, registering unsigned templates or templates that were modified after signing fails.
+ * Already registered templates that are not signed by one of the keys are unregistered.
+ * Templates are signed with `oicana pack --sign key.pem`.
+ * @param publicKeys - one or more PEM encoded Ed25519 public keys
+ * @returns the identifiers of the unregistered templates
+ * @throws if the public keys cannot be read
+ */
+export function requireSignedTemplates(publicKeys: string): string[] {
+    return require_signed_templates(publicKeys);
+}
@@ -2,28 +2,32 @@
 //!
 //! You most likely want to use the npm package `@oicana/browser` instead.
 
-use js_sys::Uint8Array;
+use js_sys::{Array, Object, Reflect, Uint8Array};
 use log::{info, warn, Level};
-use oicana_export::pdf::export_merged_pdf;
-use oicana_export::png::export_merged_png;
-use oicana_export::svg::export_merged_svg;
+use oicana_export::format::export_all;
+use oicana_export::pages::ImageExportOptions;
+use oicana_export::pdf::PdfExportOptions;
+use oicana_export::raster::{RasterFormat, ThumbnailOptions};
 use oicana_files::packed::PackedTemplate;
+use oicana_files::signature::TrustedKeys;
 use oicana_files::TemplateFiles;
 use oicana_input::input::blob::{Blob, BlobInput};
 use oicana_input::input::json::JsonInput;
 use oicana_input::{CompilationConfig, TemplateInputs};
 use oicana_world::diagnostics::DiagnosticColor;
-use oicana_world::get_current_time;
+use oicana_world::fonts::{font_families, FontCollection};
 use oicana_world::manifest::OicanaWorldFiles;
 use oicana_world::world::OicanaWorld;
+use oicana_world::{evict_cache, get_current_time, BATCH_CHUNK_SIZE};
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
 use serde_wasm_bindgen::from_value;
 use std::collections::HashMap;
 use std::io::Cursor;
-use std::sync::Mutex;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
 use typst::foundations::Bytes;
-use typst::layout::PagedDocument;
+use typst::layout::{PageRanges, PagedDocument};
 use typst::syntax::{FileId, VirtualPath};
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsValue;
@@ -32,6 +36,9 @@ use wasm_bindgen::JsValue;
 /// trying to use the template through a different method.
 pub const NOT_REGISTERED: &str = "Template is not registered";
 
+/// World of a registered template
+type TemplateWorld = OicanaWorld<Arc<PackedTemplate>>;
+
 /// Register the given template. This will read the template as a [`PackedTemplate`] and compile it
 /// once with the given inputs. The Typst [`typst::World`] will be cached and reused for subsequent
 /// calls to the other methods with the same template identifier.
@@ -57,12 +64,18 @@ pub fn register_template(
 
     let mut vec = vec![0; files.length() as usize];
     files.copy_to(&mut vec[..]);
-    let files = PackedTemplate::new(Cursor::new(vec));
+    let files = match trusted_keys().lock().unwrap().as_ref() {
+        Some(trusted_keys) => PackedTemplate::try_new_signed(Cursor::new(vec), trusted_keys),
+        None => PackedTemplate::try_new(Cursor::new(vec)),
+    }
+    .map_err(|error| format!("{error}"))?;
     let manifest = files.manifest().map_err(|error| format!("{error:?}"))?;
     println!("inserting new world for template '{template}'");
 
-    let mut world =
-        OicanaWorld::new(files, inputs, manifest).map_err(|error| format!("{error:?}"))?;
+    let mut fonts = FontCollection::new();
+    fonts.collect_with_registered(&files, &registered_fonts().lock().unwrap());
+    let mut world = OicanaWorld::with_fonts(Arc::new(files), inputs, manifest, Arc::new(fonts))
+        .map_err(|error| format!("{error:?}"))?;
     world.color = DiagnosticColor::None;
 
     let document = world.compile().map_err(|error| format!("{error:?}"))?;
@@ -70,7 +83,10 @@ pub fn register_template(
     info!("Done compiling document in {}ms", document_time - start);
 
     let result = export(&document.document, &world, export_format);
-    world_cache().lock().unwrap().insert(template, world);
+    world_cache()
+        .lock()
+        .unwrap()
+        .insert(template, Arc::new(world));
 
     result
 }
@@ -91,8 +107,7 @@ pub fn compile_template(
     let _ = console_log::init_with_level(Level::Debug);
     let start = get_current_time();
 
-    let mut cache_lock = world_cache().lock().unwrap();
-    let Some(world) = cache_lock.get_mut(&template) else {
+    let Some(world) = registered_world(&template) else {
         return Err(NOT_REGISTERED.to_owned());
     };
     let export_format: ExportFormat = from_value(export_format)
@@ -101,16 +116,195 @@ pub fn compile_template(
         .map_err(|error| format!("Failed to convert to compilation mode: {error:?}"))?;
     let mut inputs = prepare_inputs(json_inputs, blob_inputs)?;
     inputs.with_config(compilation_mode.into());
-    world.update_inputs(inputs);
 
-    let document = world.compile().map_err(|error| format!("{error:?}"))?;
+    // Like `OicanaWorld::compile`, to prevent https://github.com/typst/typst/issues/6832
+    evict_cache();
+    let document = world
+        .compile_with_inputs(inputs)
+        .map_err(|error| format!("{error:?}"))?;
+    let document_time = get_current_time();
+    if let Some(warnings) = document.warnings {
+        warn!("{warnings}");
+    }
+    info!("Done preparing document in {}ms", document_time - start);
+
+    export(&document.document, &world, export_format)
+}
+
+/// Compile the identified template once and export it into all given formats.
+///
+/// The returned array contains one output per export format in the same order.
+/// Calling this method requires a previous call to [`register_template`] with the same template
+/// identifier.
+#[wasm_bindgen]
+pub fn compile_template_formats(
+    template: String,
+    json_inputs: JsValue,
+    blob_inputs: JsValue,
+    export_formats: JsValue,
+    compilation_mode: JsValue,
+) -> Result<Array, String> {
+    console_error_panic_hook::set_once();
+    let _ = console_log::init_with_level(Level::Debug);
+    let start = get_current_time();
+
+    let Some(world) = registered_world(&template) else {
+        return Err(NOT_REGISTERED.to_owned());
+    };
+    let export_formats: Vec<ExportFormat> = from_value(export_formats)
+        .map_err(|error| format!("Failed to convert to export formats: {error:?}"))?;
+    let compilation_mode: CompilationMode = from_value(compilation_mode)
+        .map_err(|error| format!("Failed to convert to compilation mode: {error:?}"))?;
+    let mut inputs = prepare_inputs(json_inputs, blob_inputs)?;
+    inputs.with_config(compilation_mode.into());
+
+    // Like `OicanaWorld::compile`, to prevent https://github.com/typst/typst/issues/6832
+    evict_cache();
+    let document = world
+        .compile_with_inputs(inputs)
+        .map_err(|error| format!("{error:?}"))?;
     let document_time = get_current_time();
     if let Some(warnings) = document.warnings {
         warn!("{warnings}");
     }
     info!("Done preparing document in {}ms", document_time - start);
 
-    export(&document.document, world, export_format)
+    let export_formats: Vec<_> = export_formats.into_iter().map(Into::into).collect();
+    let outputs = export_all(&document.document, world.as_ref(), &export_formats)?;
+    info!(
+        "Exported document in {}ms",
+        get_current_time() - document_time
+    );
+
+    Ok(outputs
+        .iter()
+        .map(|output| bytes_to_js_array(output))
+        .collect())
+}
+
+/// Compile the identified template once for every input set and export each document.
+///
+/// `json_inputs` and `blob_inputs` are arrays with one entry per input set. The returned array
+/// contains one object per input set in the same order. It has either an `output` or an `error`
+/// property, so a failing input set does not affect the others. WASM runs single threaded, so the
+/// input sets are compiled one after another. The compilation cache is evicted after every
+/// [`BATCH_CHUNK_SIZE`] input sets.
+/// Calling this method requires a previous call to [`register_template`] with the same template
+/// identifier.
+#[wasm_bindgen]
+pub fn compile_template_batch(
+    template: String,
+    json_inputs: JsValue,
+    blob_inputs: JsValue,
+    export_format: JsValue,
+    compilation_mode: JsValue,
+) -> Result<Array, String> {
+    console_error_panic_hook::set_once();
+    let _ = console_log::init_with_level(Level::Debug);
+    let start = get_current_time();
+
+    let Some(world) = registered_world(&template) else {
+        return Err(NOT_REGISTERED.to_owned());
+    };
+    let world = world.as_ref();
+    let export_format: oicana_export::format::ExportFormat =
+        from_value::<ExportFormat>(export_format)
+            .map_err(|error| format!("Failed to convert to export format: {error:?}"))?
+            .into();
+    let compilation_mode: CompilationMode = from_value(compilation_mode)
+        .map_err(|error| format!("Failed to convert to compilation mode: {error:?}"))?;
+    let json_inputs = Array::from(&json_inputs);
+    let blob_inputs = Array::from(&blob_inputs);
+    if json_inputs.length() != blob_inputs.length() {
+        return Err(format!(
+            "Got {} sets of json inputs, but {} sets of blob inputs",
+            json_inputs.length(),
+            blob_inputs.length()
+        ));
+    }
+
+    let results = Array::new();
+    for (index, (json_inputs, blob_inputs)) in
+        json_inputs.iter().zip(blob_inputs.iter()).enumerate()
+    {
+        if index > 0 && index % BATCH_CHUNK_SIZE == 0 {
+            evict_cache();
+        }
+        let result = prepare_inputs(json_inputs, blob_inputs)
+            .and_then(|mut inputs| {
+                inputs.with_config(compilation_mode.into());
+                world
+                    .compile_with_inputs(inputs)
+                    .map_err(|error| format!("{error:?}"))
+            })
+            .and_then(|document| {
+                if let Some(warnings) = document.warnings {
+                    warn!("{warnings}");
+                }
+                oicana_export::format::export(&document.document, world, &export_format)
+            });
+
+        let item = Object::new();
+        let (key, value) = match result {
+            Ok(output) => ("output", JsValue::from(bytes_to_js_array(&output))),
+            Err(error) => ("error", JsValue::from(error)),
+        };
+        Reflect::set(&item, &JsValue::from(key), &value)
+            .map_err(|error| format!("Failed to set batch result: {error:?}"))?;
+        results.push(&item);
+    }
+    evict_cache();
+    info!(
+        "Compiled batch of {} documents in {}ms",
+        results.length(),
+        get_current_time() - start
+    );
+
+    Ok(results)
+}
+
+/// Register a font for all templates.
+///
+/// Already registered templates and templates registered afterwards can use the font. Fonts of a
+/// template take precedence over registered fonts. Returns the families of the registered fonts.
+#[wasm_bindgen]
+pub fn register_font(font: &Uint8Array) -> Result<Vec<String>, String> {
+    let data = Bytes::new(font.to_vec());
+    let families = font_families(&data);
+    if families.is_empty() {
+        return Err("The data does not contain a supported font".to_owned());
+    }
+
+    let mut registered = registered_fonts().lock().unwrap();
+    registered.push(data);
+    for world in world_cache().lock().unwrap().values_mut() {
+        let mut fonts = FontCollection::new();
+        fonts.collect_with_registered(&world.files, &registered);
+        Arc::make_mut(world).set_fonts(Arc::new(fonts));
+    }
+
+    Ok(families)
+}
+
+/// Only accept templates signed by one of the given public keys.
+///
+/// `public_keys` contains one or more PEM encoded Ed25519 public keys. Afterwards, registering
+/// unsigned templates and templates modified after signing fails. Already registered templates
+/// that are not signed by one of the keys are unregistered. Returns their identifiers.
+#[wasm_bindgen]
+pub fn require_signed_templates(public_keys: String) -> Result<Vec<String>, String> {
+    let keys = TrustedKeys::from_pem(&public_keys).map_err(|error| format!("{error}"))?;
+    let mut unsigned = Vec::new();
+    world_cache().lock().unwrap().retain(|template, world| {
+        let signed = world.files.verify_signature(&keys).is_ok();
+        if !signed {
+            unsigned.push(template.clone());
+        }
+        signed
+    });
+    *trusted_keys().lock().unwrap() = Some(keys);
+
+    Ok(unsigned)
 }
 
 /// Load all input definitions for the given template.
@@ -119,8 +313,7 @@ pub fn compile_template(
 /// identifier.
 #[wasm_bindgen]
 pub fn inputs(template: String) -> Result<String, String> {
-    let mut cache_lock = world_cache().lock().unwrap();
-    let Some(world) = cache_lock.get_mut(&template) else {
+    let Some(world) = registered_world(&template) else {
         return Err(NOT_REGISTERED.to_owned());
     };
     let template = world
@@ -139,8 +332,7 @@ pub fn inputs(template: String) -> Result<String, String> {
 /// identifier.
 #[wasm_bindgen]
 pub fn get_source(template: String, file: String) -> Result<String, String> {
-    let mut cache_lock = world_cache().lock().unwrap();
-    let Some(world) = cache_lock.get_mut(&template) else {
+    let Some(world) = registered_world(&template) else {
         return Err(NOT_REGISTERED.to_owned());
     };
     world
@@ -156,8 +348,7 @@ pub fn get_source(template: String, file: String) -> Result<String, String> {
 /// identifier.
 #[wasm_bindgen]
 pub fn get_file(template: String, file: String) -> Result<Uint8Array, String> {
-    let mut cache_lock = world_cache().lock().unwrap();
-    let Some(world) = cache_lock.get_mut(&template) else {
+    let Some(world) = registered_world(&template) else {
         return Err(NOT_REGISTERED.to_owned());
     };
     let bytes = world
@@ -184,25 +375,14 @@ fn prepare_inputs(json_inputs: JsValue, blobs: JsValue) -> Result<TemplateInputs
 
 fn export(
     document: &PagedDocument,
-    world: &OicanaWorld<PackedTemplate>,
+    world: &TemplateWorld,
     export_format: ExportFormat,
 ) -> Result<Uint8Array, String> {
-    match export_format {
-        ExportFormat::Png { pixels_per_pt } => {
-            let start_time = get_current_time();
-            let pix_map_result = export_merged_png(document, pixels_per_pt);
-            info!("Rendered image in {}ms", get_current_time() - start_time);
-            pix_map_result
-                .map_err(|error| format!("Failed to encode PNG: {error:?}"))
-                .map(|pix_map| bytes_to_js_array(&pix_map))
-        }
-        ExportFormat::Pdf => export_merged_pdf(document, world).map(|pdf| bytes_to_js_array(&pdf)),
-        ExportFormat::Svg => {
-            let svg = export_merged_svg(document);
+    let start_time = get_current_time();
+    let result = oicana_export::format::export(document, world, &export_format.into());
+    info!("Exported document in {}ms", get_current_time() - start_time);
 
-            Ok(bytes_to_js_array(&svg))
-        }
-    }
+    result.map(|bytes| bytes_to_js_array(&bytes))
 }
 
 fn bytes_to_js_array(bytes: &[u8]) -> Uint8Array {
@@ -212,7 +392,7 @@ fn bytes_to_js_array(bytes: &[u8]) -> Uint8Array {
     uint8_array
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, Copy)]
 enum CompilationMode {
     #[serde(alias = "production")]
     Production,
@@ -229,15 +409,156 @@ impl From<CompilationMode> for oicana_input::CompilationConfig {
     }
 }
 
+/// Unknown fields are rejected, so options like a quality for lossless WebP images fail loudly.
 #[derive(Deserialize)]
-#[serde(tag = "format")]
+#[serde(tag = "format", deny_unknown_fields)]
 enum ExportFormat {
     #[serde(alias = "png")]
-    Png { pixels_per_pt: f32 },
+    Png {
+        pixels_per_pt: f32,
+        #[serde(default)]
+        pages: Option<PageRange>,
+    },
+    #[serde(alias = "jpeg")]
+    Jpeg {
+        pixels_per_pt: f32,
+        quality: u8,
+        #[serde(default)]
+        pages: Option<PageRange>,
+    },
+    #[serde(alias = "webp")]
+    WebP {
+        pixels_per_pt: f32,
+        #[serde(default)]
+        pages: Option<PageRange>,
+    },
+    /// The first page as JPEG, scaled to fit into the given bounding box in pixels.
+    #[serde(alias = "thumbnail")]
+    Thumbnail {
+        max_width: u32,
+        max_height: u32,
+        quality: u8,
+    },
     #[serde(alias = "pdf")]
-    Pdf,
+    Pdf {
+        #[serde(default)]
+        encryption: Option<PdfEncryption>,
+    },
     #[serde(alias = "svg")]
-    Svg,
+    Svg {
+        #[serde(default)]
+        pages: Option<PageRange>,
+    },
+    /// The plain text of all pages, separated by form feeds.
+    #[serde(alias = "text")]
+    Text,
+}
+
+impl From<ExportFormat> for oicana_export::format::ExportFormat {
+    fn from(value: ExportFormat) -> Self {
+        let image_options = |pages: Option<PageRange>, pixels_per_pt: f32| ImageExportOptions {
+            pages: pages.map(Into::into),
+            pixels_per_pt,
+            ..ImageExportOptions::default()
+        };
+
+        match value {
+            ExportFormat::Png {
+                pixels_per_pt,
+                pages,
+            } => Self::Png(image_options(pages, pixels_per_pt)),
+            ExportFormat::Jpeg {
+                pixels_per_pt,
+                quality,
+                pages,
+            } => Self::Raster(
+                image_options(pages, pixels_per_pt),
+                RasterFormat::Jpeg { quality },
+            ),
+            ExportFormat::WebP {
+                pixels_per_pt,
+                pages,
+            } => Self::Raster(image_options(pages, pixels_per_pt), RasterFormat::WebP),
+            ExportFormat::Thumbnail {
+                max_width,
+                max_height,
+                quality,
+            } => Self::Thumbnail(ThumbnailOptions {
+                max_width,
+                max_height,
+                format: RasterFormat::Jpeg { quality },
+            }),
+            ExportFormat::Pdf { encryption } => Self::Pdf(PdfExportOptions {
+                encryption: encryption.map(Into::into),
+            }),
+            ExportFormat::Svg { pages } => Self::Svg(image_options(pages, 1.0)),
+            ExportFormat::Text => Self::Text,
+        }
+    }
+}
+
+/// One-indexed, inclusive range of pages to export.
+///
+/// A missing bound leaves the range open on that side.
+#[derive(Deserialize)]
+struct PageRange {
+    #[serde(default)]
+    first: Option<NonZeroUsize>,
+    #[serde(default)]
+    last: Option<NonZeroUsize>,
+}
+
+impl From<PageRange> for PageRanges {
+    fn from(value: PageRange) -> Self {
+        PageRanges::new(vec![value.first..=value.last])
+    }
+}
+
+#[derive(Deserialize)]
+struct PdfEncryption {
+    #[serde(default)]
+    user_password: String,
+    owner_password: String,
+    #[serde(default)]
+    permissions: PdfPermissions,
+}
+
+impl From<PdfEncryption> for oicana_export::pdf::PdfEncryption {
+    fn from(value: PdfEncryption) -> Self {
+        oicana_export::pdf::PdfEncryption {
+            user_password: value.user_password,
+            owner_password: value.owner_password,
+            permissions: value.permissions.into(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+struct PdfPermissions {
+    print: bool,
+    copy: bool,
+    modify: bool,
+}
+
+impl Default for PdfPermissions {
+    fn default() -> Self {
+        PdfPermissions {
+            print: true,
+            copy: true,
+            modify: true,
+        }
+    }
+}
+
+impl From<PdfPermissions> for oicana_export::pdf::PdfPermissions {
+    fn from(value: PdfPermissions) -> Self {
+        oicana_export::pdf::PdfPermissions {
+            print: value.print,
+            copy: value.copy,
+            modify: value.modify,
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -272,8 +593,22 @@ fn add_json_inputs(inputs: &mut TemplateInputs, json_inputs: JsValue) -> Result<
     Ok(())
 }
 
-fn world_cache() -> &'static Mutex<HashMap<String, OicanaWorld<PackedTemplate>>> {
-    static ZIPPED_WORLD: OnceCell<Mutex<HashMap<String, OicanaWorld<PackedTemplate>>>> =
-        OnceCell::new();
+/// Get a registered template without holding the cache lock.
+fn registered_world(template: &str) -> Option<Arc<TemplateWorld>> {
+    world_cache().lock().unwrap().get(template).cloned()
+}
+
+fn world_cache() -> &'static Mutex<HashMap<String, Arc<TemplateWorld>>> {
+    static ZIPPED_WORLD: OnceCell<Mutex<HashMap<String, Arc<TemplateWorld>>>> = OnceCell::new();
     ZIPPED_WORLD.get_or_init(|| Mutex::new(HashMap::new()))
 }
+
+fn trusted_keys() -> &'static Mutex<Option<TrustedKeys>> {
+    static TRUSTED_KEYS: OnceCell<Mutex<Option<TrustedKeys>>> = OnceCell::new();
+    TRUSTED_KEYS.get_or_init(|| Mutex::new(None))
+}
+
+fn registered_fonts() -> &'static Mutex<Vec<Bytes>> {
+    static REGISTERED_FONTS: OnceCell<Mutex<Vec<Bytes>>> = OnceCell::new();
+    REGISTERED_FONTS.get_or_init(|| Mutex::new(Vec::new()))
+}
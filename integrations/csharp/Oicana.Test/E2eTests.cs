@@ -29,6 +29,20 @@ public class E2ETests
         document.CopyTo(fileStream);
     }
 
+    [Fact]
+    public void WebP()
+    {
+        var template = new Template(_templateFile);
+
+        var document = template.Compile(new List<TemplateJsonInput>(), new List<TemplateBlobInput>(), CompilationOptions.WebP(1.0f, CompilationMode.Development));
+        using var memoryStream = new MemoryStream();
+        document.CopyTo(memoryStream);
+        var bytes = memoryStream.ToArray();
+
+        System.Text.Encoding.ASCII.GetString(bytes, 0, 4).Should().Be("RIFF");
+        System.Text.Encoding.ASCII.GetString(bytes, 8, 4).Should().Be("WEBP");
+    }
+
     [Fact]
     public void Production()
     {
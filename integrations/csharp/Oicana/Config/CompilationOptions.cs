@@ -8,17 +8,25 @@ public class CompilationOptions
     internal CompilationMode compilationMode;
     internal CompilationTarget compilationTarget;
     internal float? pixelsPerPt;
+    internal PdfEncryption? pdfEncryption;
+    internal uint? firstPage;
+    internal uint? lastPage;
+    internal byte? quality;
+    internal uint? thumbnailWidth;
+    internal uint? thumbnailHeight;
 
     /// <summary>
     /// Create new compilation options for compiling to PDF
     /// </summary>
     /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
-    public static CompilationOptions Pdf(CompilationMode mode = CompilationMode.Production)
+    /// <param name="encryption">Optional password protection for the PDF. Encrypted PDFs are not PDF/A conform.</param>
+    public static CompilationOptions Pdf(CompilationMode mode = CompilationMode.Production, PdfEncryption? encryption = null)
     {
         return new CompilationOptions()
         {
             compilationTarget = CompilationTarget.Pdf,
             compilationMode = mode,
+            pdfEncryption = encryption,
         };
     }
 
@@ -27,13 +35,79 @@ public class CompilationOptions
     /// </summary>
     /// <param name="pixelsPerPt">The number of pixels per pt. Higher numbers take longer, but create sharper images.</param>
     /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
-    public static CompilationOptions Png(float pixelsPerPt = 1.0f, CompilationMode mode = CompilationMode.Production)
+    /// <param name="firstPage">The first page to render (one-indexed). Defaults to the first page of the document.</param>
+    /// <param name="lastPage">The last page to render (inclusive). Defaults to the last page of the document.</param>
+    public static CompilationOptions Png(float pixelsPerPt = 1.0f, CompilationMode mode = CompilationMode.Production, uint? firstPage = null, uint? lastPage = null)
     {
         return new CompilationOptions()
         {
             compilationTarget = CompilationTarget.Png,
             compilationMode = mode,
             pixelsPerPt = pixelsPerPt,
+            firstPage = firstPage,
+            lastPage = lastPage,
+        };
+    }
+
+    /// <summary>
+    /// Create new compilation options for compiling to JPEG
+    /// </summary>
+    /// <param name="pixelsPerPt">The number of pixels per pt. Higher numbers take longer, but create sharper images.</param>
+    /// <param name="quality">Quality between 1 (smallest file) and 100 (best quality).</param>
+    /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
+    /// <param name="firstPage">The first page to render (one-indexed). Defaults to the first page of the document.</param>
+    /// <param name="lastPage">The last page to render (inclusive). Defaults to the last page of the document.</param>
+    /// <remarks>JPEG does not support transparency. Transparent areas are rendered white.</remarks>
+    public static CompilationOptions Jpeg(float pixelsPerPt = 1.0f, byte quality = 85, CompilationMode mode = CompilationMode.Production, uint? firstPage = null, uint? lastPage = null)
+    {
+        return new CompilationOptions()
+        {
+            compilationTarget = CompilationTarget.Jpeg,
+            compilationMode = mode,
+            pixelsPerPt = pixelsPerPt,
+            quality = quality,
+            firstPage = firstPage,
+            lastPage = lastPage,
+        };
+    }
+
+    /// <summary>
+    /// Create new compilation options for compiling to WebP
+    /// </summary>
+    /// <param name="pixelsPerPt">The number of pixels per pt. Higher numbers take longer, but create sharper images.</param>
+    /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
+    /// <param name="firstPage">The first page to render (one-indexed). Defaults to the first page of the document.</param>
+    /// <param name="lastPage">The last page to render (inclusive). Defaults to the last page of the document.</param>
+    /// <remarks>The WebP image is encoded lossless and has no quality setting.</remarks>
+    public static CompilationOptions WebP(float pixelsPerPt = 1.0f, CompilationMode mode = CompilationMode.Production, uint? firstPage = null, uint? lastPage = null)
+    {
+        return new CompilationOptions()
+        {
+            compilationTarget = CompilationTarget.WebP,
+            compilationMode = mode,
+            pixelsPerPt = pixelsPerPt,
+            firstPage = firstPage,
+            lastPage = lastPage,
+        };
+    }
+
+    /// <summary>
+    /// Create new compilation options for a JPEG thumbnail of the first page
+    /// </summary>
+    /// <param name="maxWidth">Maximum width of the thumbnail in pixels.</param>
+    /// <param name="maxHeight">Maximum height of the thumbnail in pixels.</param>
+    /// <param name="quality">Quality between 1 (smallest file) and 100 (best quality).</param>
+    /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
+    /// <remarks>The page keeps its aspect ratio and is scaled to fit into the bounding box.</remarks>
+    public static CompilationOptions Thumbnail(uint maxWidth, uint maxHeight, byte quality = 85, CompilationMode mode = CompilationMode.Production)
+    {
+        return new CompilationOptions()
+        {
+            compilationTarget = CompilationTarget.Thumbnail,
+            compilationMode = mode,
+            quality = quality,
+            thumbnailWidth = maxWidth,
+            thumbnailHeight = maxHeight,
         };
     }
 
@@ -41,12 +115,30 @@ public class CompilationOptions
     /// Create new compilation options for compiling to SVG
     /// </summary>
     /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
-    public static CompilationOptions Svg(CompilationMode mode = CompilationMode.Production)
+    /// <param name="firstPage">The first page to render (one-indexed). Defaults to the first page of the document.</param>
+    /// <param name="lastPage">The last page to render (inclusive). Defaults to the last page of the document.</param>
+    public static CompilationOptions Svg(CompilationMode mode = CompilationMode.Production, uint? firstPage = null, uint? lastPage = null)
     {
         return new CompilationOptions()
         {
             compilationTarget = CompilationTarget.Svg,
             compilationMode = mode,
+            firstPage = firstPage,
+            lastPage = lastPage,
+        };
+    }
+
+    /// <summary>
+    /// Create new compilation options for extracting the plain text of the document
+    /// </summary>
+    /// <param name="mode">The compilation mode defines what fallback values can be used for template inputs.</param>
+    /// <remarks>The text is UTF-8 encoded. Pages are separated by form feeds.</remarks>
+    public static CompilationOptions Text(CompilationMode mode = CompilationMode.Production)
+    {
+        return new CompilationOptions()
+        {
+            compilationTarget = CompilationTarget.Text,
+            compilationMode = mode,
         };
     }
 }
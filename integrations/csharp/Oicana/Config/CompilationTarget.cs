@@ -20,4 +20,20 @@ public enum CompilationTarget
     /// Render the template as SVG file.
     /// </summary>
     Svg = 2,
+    /// <summary>
+    /// Render the template into a lossy JPEG image.
+    /// </summary>
+    Jpeg = 3,
+    /// <summary>
+    /// Render the template into a lossless WebP image.
+    /// </summary>
+    WebP = 4,
+    /// <summary>
+    /// Render the first page as small JPEG preview.
+    /// </summary>
+    Thumbnail = 5,
+    /// <summary>
+    /// Extract the plain text of all pages as UTF-8, separated by form feeds.
+    /// </summary>
+    Text = 6,
 }
@@ -0,0 +1,45 @@
+namespace Oicana.Config;
+
+/// <summary>
+/// Password protection for PDF exports.
+///
+/// The document is encrypted with AES-256.
+/// </summary>
+/// <remarks>PDF/A does not allow encryption. Encrypted documents are not PDF/A-3b conform.</remarks>
+public class PdfEncryption
+{
+    /// <summary>
+    /// Password required to open the document.
+    /// An empty user password allows everyone to open the document, but the permissions are still enforced.
+    /// </summary>
+    public string UserPassword { get; init; } = "";
+
+    private readonly string _ownerPassword = "";
+
+    /// <summary>
+    /// Password granting full access to the document.
+    /// </summary>
+    /// <exception cref="ArgumentException">If the password is empty.</exception>
+    public required string OwnerPassword
+    {
+        get => _ownerPassword;
+        init => _ownerPassword = string.IsNullOrEmpty(value)
+            ? throw new ArgumentException("The owner password must not be empty.", nameof(OwnerPassword))
+            : value;
+    }
+
+    /// <summary>
+    /// Allow printing the document.
+    /// </summary>
+    public bool AllowPrint { get; init; } = true;
+
+    /// <summary>
+    /// Allow copying text and graphics from the document.
+    /// </summary>
+    public bool AllowCopy { get; init; } = true;
+
+    /// <summary>
+    /// Allow modifying the document, including annotations and form fields.
+    /// </summary>
+    public bool AllowModify { get; init; } = true;
+}
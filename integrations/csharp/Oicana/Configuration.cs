@@ -15,4 +15,33 @@ public static class Configuration
     {
         OicanaFfi.Configure(coloring);
     }
+
+    /// <summary>
+    /// Register a font for all templates.
+    ///
+    /// Already registered templates and templates registered afterwards can use the font.
+    /// Fonts of a template take precedence over registered fonts.
+    /// </summary>
+    /// <param name="font">Data of a font file or font collection, e.g. a TTF or OTF file.</param>
+    /// <exception cref="OicanaException">If the data does not contain a supported font.</exception>
+    /// <returns>The families of the registered fonts.</returns>
+    public static IReadOnlyList<string> RegisterFont(byte[] font)
+    {
+        return OicanaFfi.RegisterFont(font);
+    }
+
+    /// <summary>
+    /// Only accept templates signed by one of the given public keys.
+    ///
+    /// Afterwards, loading unsigned templates or templates that were modified after signing fails.
+    /// Already loaded templates that are not signed by one of the keys are unloaded and have to be loaded again.
+    /// Templates are signed with <c>oicana pack --sign key.pem</c>.
+    /// </summary>
+    /// <param name="publicKeys">One or more PEM encoded Ed25519 public keys, e.g. from <c>openssl pkey -in key.pem -pubout</c>.</param>
+    /// <exception cref="OicanaException">If the public keys cannot be read.</exception>
+    /// <returns>The identifiers of the unloaded templates.</returns>
+    public static IReadOnlyList<string> RequireSignedTemplates(string publicKeys)
+    {
+        return OicanaFfi.RequireSignedTemplates(publicKeys);
+    }
 }
@@ -0,0 +1,8 @@
+namespace Oicana.Inputs;
+
+/// <summary>
+/// All inputs for one compilation of a batch
+/// </summary>
+/// <param name="JsonInputs">Json inputs for the compilation.</param>
+/// <param name="BlobInputs">Blob inputs for the compilation.</param>
+public record TemplateInputSet(IList<TemplateJsonInput> JsonInputs, IList<TemplateBlobInput> BlobInputs);
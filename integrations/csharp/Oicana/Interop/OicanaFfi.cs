@@ -1,7 +1,9 @@
+using System.Runtime.ExceptionServices;
 using System.Runtime.InteropServices;
 using System.Text;
 using System.Text.RegularExpressions;
 using Oicana.Inputs;
+using Oicana.Template;
 
 namespace Oicana.Interop;
 
@@ -60,6 +62,157 @@ internal static class OicanaFfi
         return HandleBuffer(buffer);
     }
 
+    /// <summary>
+    /// Compile a template with the given id and inputs and write the export to the given stream.
+    /// </summary>
+    /// <remarks>
+    /// The export is written in chunks instead of being copied into a returned buffer.
+    /// Images, SVG, and text are written while they are encoded. PDFs are encoded in memory as a whole first.
+    /// Writing to a slow stream does not block other calls for the same template.
+    /// </remarks>
+    /// <param name="templateId">Identifier of the template for the internal cache.</param>
+    /// <param name="jsonInputs">Json inputs for the compilation.</param>
+    /// <param name="blobInputs">Blob inputs for the compilation.</param>
+    /// <param name="compilationOptions">Options for the template compilation.</param>
+    /// <param name="output">Stream to write the export to.</param>
+    /// <exception cref="OicanaException">If the template compilation fails.</exception>
+    public static void CompileTemplateToStream(string templateId, IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, Oicana.Config.CompilationOptions compilationOptions, Stream output)
+    {
+        PreparedInputs preparedInputs = PrepareInputs(jsonInputs, blobInputs);
+        Exception? sinkException = null;
+        var chunk = Array.Empty<byte>();
+        WriteCallback sink = (data, len) =>
+        {
+            try
+            {
+                if (chunk.Length < len)
+                {
+                    chunk = new byte[len];
+                }
+                Marshal.Copy(data, chunk, 0, (int)len);
+                output.Write(chunk, 0, (int)len);
+                return true;
+            }
+            catch (Exception exception)
+            {
+                // Exceptions must not cross the native frames, the export is aborted instead.
+                sinkException = exception;
+                return false;
+            }
+        };
+
+        var buffer = OicanaFfiInternal.unsafe_compile_template_to_sink(templateId, preparedInputs.JsonInputs, preparedInputs.BlobInputs, ConvertCompileOptions(compilationOptions), sink);
+
+        GC.KeepAlive(sink);
+        preparedInputs.FreeAll();
+
+        if (sinkException != null)
+        {
+            OicanaFfiInternal.unsafe_free_buffer(buffer);
+            ExceptionDispatchInfo.Capture(sinkException).Throw();
+        }
+        HandleBuffer(buffer).Dispose();
+    }
+
+    /// <summary>
+    /// Compile a template with the given id and inputs once and export it to all given <see cref="Oicana.Config.CompilationTarget"/>s.
+    /// </summary>
+    /// <param name="templateId">Identifier of the template for the internal cache.</param>
+    /// <param name="jsonInputs">Json inputs for the compilation.</param>
+    /// <param name="blobInputs">Blob inputs for the compilation.</param>
+    /// <param name="compilationOptions">One entry per export. All options must use the same compilation mode.</param>
+    /// <exception cref="OicanaException">If the template compilation or any export fails.</exception>
+    /// <exception cref="ArgumentException">If the compilation options use different compilation modes.</exception>
+    /// <returns>One stream per compilation options in the same order.</returns>
+    public static IList<Stream> CompileTemplateFormats(string templateId, IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, IList<Oicana.Config.CompilationOptions> compilationOptions)
+    {
+        if (compilationOptions.Select(options => options.compilationMode).Distinct().Count() > 1)
+        {
+            throw new ArgumentException("All compilation options must use the same compilation mode.", nameof(compilationOptions));
+        }
+
+        PreparedInputs preparedInputs = PrepareInputs(jsonInputs, blobInputs);
+        var optionsSize = Marshal.SizeOf(typeof(CompilationOptions));
+        var optionsPtr = Marshal.AllocHGlobal(compilationOptions.Count * optionsSize);
+        for (int i = 0; i < compilationOptions.Count; i++)
+        {
+            Marshal.StructureToPtr(ConvertCompileOptions(compilationOptions[i]), optionsPtr + i * optionsSize, false);
+        }
+        var outputsPtr = Marshal.AllocHGlobal(compilationOptions.Count * Marshal.SizeOf(typeof(Buffer)));
+        var outputs = new SliceMutBuffer(outputsPtr, (ulong)compilationOptions.Count);
+
+        var buffer = OicanaFfiInternal.unsafe_compile_template_formats(templateId, preparedInputs.JsonInputs, preparedInputs.BlobInputs, new SliceCompilationOptions(optionsPtr, (ulong)compilationOptions.Count), outputs);
+
+        preparedInputs.FreeAll();
+        for (int i = 0; i < compilationOptions.Count; i++)
+        {
+            Marshal.DestroyStructure(optionsPtr + i * optionsSize, typeof(CompilationOptions));
+        }
+        Marshal.FreeHGlobal(optionsPtr);
+
+        try
+        {
+            // Throws for errors, the output buffers are only written on success.
+            HandleBuffer(buffer).Dispose();
+            return outputs.Copied.Select(output => (Stream)new RustMemoryStream(output)).ToList();
+        }
+        finally
+        {
+            Marshal.FreeHGlobal(outputsPtr);
+        }
+    }
+
+    /// <summary>
+    /// Compile a template with the given id once for every input set and export each document to the specified <see cref="Oicana.Config.CompilationTarget"/>.
+    /// </summary>
+    /// <param name="templateId">Identifier of the template for the internal cache.</param>
+    /// <param name="inputSets">One set of inputs per compilation.</param>
+    /// <param name="compilationOptions">Options for all template compilations.</param>
+    /// <exception cref="OicanaException">If the batch could not be compiled at all, for example because the template is not registered.</exception>
+    /// <returns>One result per input set in the same order.</returns>
+    public static IList<BatchResult> CompileTemplateBatch(string templateId, IList<TemplateInputSet> inputSets, Oicana.Config.CompilationOptions compilationOptions)
+    {
+        var preparedInputs = inputSets.Select(inputSet => PrepareInputs(inputSet.JsonInputs, inputSet.BlobInputs)).ToList();
+        var inputSetSize = Marshal.SizeOf(typeof(FfiInputSet));
+        var inputSetsPtr = Marshal.AllocHGlobal(inputSets.Count * inputSetSize);
+        for (int i = 0; i < preparedInputs.Count; i++)
+        {
+            var inputSet = new FfiInputSet() { json_inputs = preparedInputs[i].JsonInputs, blob_inputs = preparedInputs[i].BlobInputs };
+            Marshal.StructureToPtr(inputSet, inputSetsPtr + i * inputSetSize, false);
+        }
+        var outputsPtr = Marshal.AllocHGlobal(inputSets.Count * Marshal.SizeOf(typeof(Buffer)));
+        var outputs = new SliceMutBuffer(outputsPtr, (ulong)inputSets.Count);
+
+        var buffer = OicanaFfiInternal.unsafe_compile_template_batch(templateId, new SliceFfiInputSet(inputSetsPtr, (ulong)inputSets.Count), ConvertCompileOptions(compilationOptions), outputs);
+
+        foreach (var prepared in preparedInputs)
+        {
+            prepared.FreeAll();
+        }
+        Marshal.FreeHGlobal(inputSetsPtr);
+
+        try
+        {
+            // Throws for errors, the output buffers are only written on success.
+            HandleBuffer(buffer).Dispose();
+            return outputs.Copied.Select(output =>
+            {
+                try
+                {
+                    return new BatchResult(HandleBuffer(output), null);
+                }
+                catch (OicanaException error)
+                {
+                    return new BatchResult(null, error);
+                }
+            }).ToList();
+        }
+        finally
+        {
+            Marshal.FreeHGlobal(outputsPtr);
+        }
+    }
+
     /// <summary>
     /// Register and compile a template with the given id and inputs and export it to the specified <see cref="Oicana.Config.CompilationTarget"/>.
     /// </summary>
@@ -95,6 +248,27 @@ internal static class OicanaFfi
         OicanaFfiInternal.unregister_template(id);
     }
 
+    /// <summary>
+    /// Register a font for all templates.
+    /// </summary>
+    /// <param name="font">Data of a font file or font collection.</param>
+    /// <exception cref="OicanaException">If the data does not contain a supported font.</exception>
+    /// <returns>The families of the registered fonts.</returns>
+    public static IReadOnlyList<string> RegisterFont(byte[] font)
+    {
+        GCHandle fontHandle = GCHandle.Alloc(font, GCHandleType.Pinned);
+        IntPtr fontPointer = fontHandle.AddrOfPinnedObject();
+        var fontBuffer = new Buffer() { data = fontPointer, error = false, len = (uint)font.Length };
+
+        var buffer = OicanaFfiInternal.unsafe_register_font(fontBuffer);
+
+        fontHandle.Free();
+
+        using var stream = HandleBuffer(buffer);
+        using var reader = new StreamReader(stream, Encoding.UTF8);
+        return reader.ReadToEnd().Split('\n');
+    }
+
     /// <summary>
     /// Configure Oicana.
     /// </summary>
@@ -122,6 +296,22 @@ internal static class OicanaFfi
         });
     }
 
+    /// <summary>
+    /// Only accept templates signed by one of the given public keys.
+    /// </summary>
+    /// <param name="publicKeys">One or more PEM encoded Ed25519 public keys.</param>
+    /// <exception cref="OicanaException">If the public keys cannot be read.</exception>
+    /// <returns>The identifiers of registered templates that were unregistered, because they are not signed by one of the keys.</returns>
+    public static IReadOnlyList<string> RequireSignedTemplates(string publicKeys)
+    {
+        var buffer = OicanaFfiInternal.require_signed_templates(publicKeys);
+
+        using var stream = HandleBuffer(buffer);
+        using var reader = new StreamReader(stream, Encoding.UTF8);
+        var templates = reader.ReadToEnd();
+        return templates.Length == 0 ? Array.Empty<string>() : templates.Split('\n');
+    }
+
     private record PreparedInputs(IntPtr JsonInputsPtr, SliceFfiJsonInput JsonInputs, IntPtr BlobsInputsPtr, SliceFfiBlobInput BlobInputs, List<GCHandle> BlobHandles)
     {
         internal readonly IntPtr JsonInputsPtr = JsonInputsPtr;
@@ -161,7 +351,34 @@ internal static class OicanaFfi
         {
             target = ConvertCompileTarget(compilationOptions.compilationTarget),
             mode = ConvertCompilationMode(compilationOptions.compilationMode),
-            px_per_pt = compilationOptions.pixelsPerPt ?? 1.0f
+            px_per_pt = compilationOptions.pixelsPerPt ?? 1.0f,
+            pdf_encryption = ConvertPdfEncryption(compilationOptions.pdfEncryption),
+            pages = new PageRange()
+            {
+                first = compilationOptions.firstPage ?? 0,
+                last = compilationOptions.lastPage ?? 0,
+            },
+            quality = compilationOptions.quality ?? 0,
+            thumbnail_width = compilationOptions.thumbnailWidth ?? 0,
+            thumbnail_height = compilationOptions.thumbnailHeight ?? 0,
+        };
+    }
+
+    internal static Oicana.Interop.PdfEncryption ConvertPdfEncryption(Oicana.Config.PdfEncryption? encryption)
+    {
+        if (encryption == null)
+        {
+            return new PdfEncryption() { enabled = false };
+        }
+
+        return new PdfEncryption()
+        {
+            enabled = true,
+            user_password = encryption.UserPassword,
+            owner_password = encryption.OwnerPassword,
+            allow_print = encryption.AllowPrint,
+            allow_copy = encryption.AllowCopy,
+            allow_modify = encryption.AllowModify,
         };
     }
 
@@ -175,6 +392,14 @@ internal static class OicanaFfi
                 return Oicana.Interop.CompilationTarget.Png;
             case Oicana.Config.CompilationTarget.Svg:
                 return Oicana.Interop.CompilationTarget.Svg;
+            case Oicana.Config.CompilationTarget.Jpeg:
+                return Oicana.Interop.CompilationTarget.Jpeg;
+            case Oicana.Config.CompilationTarget.WebP:
+                return Oicana.Interop.CompilationTarget.WebP;
+            case Oicana.Config.CompilationTarget.Thumbnail:
+                return Oicana.Interop.CompilationTarget.Thumbnail;
+            case Oicana.Config.CompilationTarget.Text:
+                return Oicana.Interop.CompilationTarget.Text;
         }
         throw new ArgumentException($"The compile target {nameof(compilationTarget)} is not supported.");
     }
@@ -53,6 +53,69 @@ namespace Oicana.Interop
         [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "unsafe_compile_template_once")]
         public static extern Buffer unsafe_compile_template_once(Buffer files, SliceFfiJsonInput json_inputs, SliceFfiBlobInput blob_inputs, CompilationOptions compilation_options);
 
+        /// Compile the template with the given identifier once and export it into multiple formats
+        ///
+        /// This method requires a previous successful call to [`unsafe_register_template()`].
+        /// The compilation mode is taken from the first compilation options.
+        /// On success, `outputs[i]` holds the export for `compilation_options[i]` and must be freed
+        /// with [`unsafe_free_buffer`]. The returned buffer is empty on success and contains the error
+        /// message otherwise. On error, no output buffers are written.
+        ///
+        /// # Safety
+        ///
+        /// The caller is responsible for ensuring that the provided
+        /// `template`, `json_inputs`, `blob_inputs`, and `compilation_options` pointers are valid
+        /// and non-null, and that `outputs` has the same length as `compilation_options`.
+        ///
+        /// Additionally, the caller must ensure that the blob input buffers are not modified
+        /// concurrently while this function is executing.
+        [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "unsafe_compile_template_formats")]
+        public static extern Buffer unsafe_compile_template_formats(string template, SliceFfiJsonInput json_inputs, SliceFfiBlobInput blob_inputs, SliceCompilationOptions compilation_options, SliceMutBuffer outputs);
+
+        /// Compile the template with the given identifier once for every input set
+        ///
+        /// This method requires a previous successful call to [`unsafe_register_template()`].
+        /// The input sets are compiled in parallel. `outputs[i]` holds the result for `input_sets[i]`
+        /// and must be freed with [`unsafe_free_buffer`]. Each output is either the exported document
+        /// or an error for that input set. The returned buffer is empty on success and contains the
+        /// error message if the batch could not be compiled at all. In that case, no output buffers
+        /// are written.
+        ///
+        /// # Safety
+        ///
+        /// The caller is responsible for ensuring that the provided `template`, `input_sets`, and
+        /// `compilation_options` pointers are valid and non-null, and that `outputs` has the same
+        /// length as `input_sets`.
+        ///
+        /// Additionally, the caller must ensure that the blob input buffers are not modified
+        /// concurrently while this function is executing.
+        [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "unsafe_compile_template_batch")]
+        public static extern Buffer unsafe_compile_template_batch(string template, SliceFfiInputSet input_sets, CompilationOptions compilation_options, SliceMutBuffer outputs);
+
+        /// Compile the template with the given identifier and stream the export into `sink`
+        ///
+        /// This method requires a previous successful call to [`unsafe_register_template()`].
+        /// The export is passed to `sink` in chunks instead of being copied into a returned buffer.
+        /// Images, SVG, and text are passed on while they are encoded. PDFs are still encoded and,
+        /// if requested, encrypted in memory as a whole first, so for PDF only the copy of the finished
+        /// document across the FFI boundary is avoided. The pointer handed to `sink` is only valid
+        /// during that call.
+        /// `sink` is only called after the compilation and does not block other calls for the same
+        /// template, so a slow destination doesn't hold up other compilations.
+        /// If `sink` returns `false`, the export is aborted. The returned buffer is empty on success
+        /// and contains the error message otherwise.
+        ///
+        /// # Safety
+        ///
+        /// The caller is responsible for ensuring that the provided
+        /// `template`, `json_inputs`, and `blob_inputs` pointers are valid and non-null,
+        /// and that `sink` is a valid function pointer.
+        ///
+        /// Additionally, the caller must ensure that the blob input buffers are not modified
+        /// concurrently while this function is executing.
+        [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "unsafe_compile_template_to_sink")]
+        public static extern Buffer unsafe_compile_template_to_sink(string template, SliceFfiJsonInput json_inputs, SliceFfiBlobInput blob_inputs, CompilationOptions compilation_options, WriteCallback sink);
+
         /// Register a template for the given identifier
         ///
         /// After a successful call to this method, use [`unsafe_compile_template()`] for compiling
@@ -117,6 +180,28 @@ namespace Oicana.Interop
         [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "configure")]
         public static extern Buffer configure(Config config);
 
+        /// Only accept templates signed by one of the given public keys.
+        ///
+        /// `public_keys` contains one or more PEM encoded Ed25519 public keys. Afterwards, registering or
+        /// compiling unsigned templates and templates modified after signing fails. Templates that are
+        /// already registered and not signed by one of the keys are unregistered. On success, the buffer
+        /// contains the identifiers of the unregistered templates separated by line breaks.
+        [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "require_signed_templates")]
+        public static extern Buffer require_signed_templates(string public_keys);
+
+        /// Register a font for all templates.
+        ///
+        /// Registered templates are updated to use the font. Templates registered afterwards can use it
+        /// as well. On success, the buffer contains the families of the registered fonts separated by
+        /// line breaks.
+        ///
+        /// # Safety
+        ///
+        /// The caller is responsible for ensuring that the provided `font` pointer is valid and non-null,
+        /// and that the data is properly aligned and initialized.
+        [DllImport(NativeLib, CallingConvention = CallingConvention.Cdecl, EntryPoint = "unsafe_register_font")]
+        public static extern Buffer unsafe_register_font(Buffer font);
+
     }
 
     /// The mode of compilation
@@ -142,6 +227,14 @@ namespace Oicana.Interop
         Png = 1,
         /// Render the template as SVG file.
         Svg = 2,
+        /// Render the template into a lossy JPEG image.
+        Jpeg = 3,
+        /// Render the template into a lossless WebP image.
+        WebP = 4,
+        /// Render the first page as JPEG thumbnail fitting into a bounding box.
+        Thumbnail = 5,
+        /// Extract the plain text of all pages as UTF-8, separated by form feeds.
+        Text = 6,
     }
 
     /// Formats that the compiled documents can be rendered into.
@@ -181,6 +274,21 @@ namespace Oicana.Interop
         /// Pixels per pt
         /// Only used for PNG export
         public float px_per_pt;
+        /// Password protection
+        /// Only used for PDF export
+        public PdfEncryption pdf_encryption;
+        /// Pages to export
+        /// Only used for PNG, JPEG, WebP, and SVG export
+        public PageRange pages;
+        /// Quality between 1 and 100
+        /// Only used for JPEG and thumbnail export, WebP images are lossless and have no quality setting
+        public byte quality;
+        /// Maximum width of the thumbnail in pixels
+        /// Only used for thumbnail export
+        public uint thumbnail_width;
+        /// Maximum height of the thumbnail in pixels
+        /// Only used for thumbnail export
+        public uint thumbnail_height;
     }
 
     /// Oicana Configuration.
@@ -205,6 +313,17 @@ namespace Oicana.Interop
         public string meta;
     }
 
+    /// One set of inputs for a batch compilation
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct FfiInputSet
+    {
+        /// Blob inputs of this input set.
+        public SliceFfiBlobInput blob_inputs;
+        /// JSON inputs of this input set.
+        public SliceFfiJsonInput json_inputs;
+    }
+
     /// A collection of string pairs representing JSON inputs
     [Serializable]
     [StructLayout(LayoutKind.Sequential)]
@@ -216,6 +335,110 @@ namespace Oicana.Interop
         public string key;
     }
 
+    /// One-indexed, inclusive range of pages.
+    ///
+    /// A bound of `0` leaves the range open on that side.
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct PageRange
+    {
+        /// First page to export.
+        public uint first;
+        /// Last page to export.
+        public uint last;
+    }
+
+    /// Password protection for PDF exports.
+    ///
+    /// Encrypted PDFs use AES-256 and are not PDF/A conform.
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct PdfEncryption
+    {
+        /// Whether the exported PDF should be encrypted.
+        [MarshalAs(UnmanagedType.I1)]
+        public bool enabled;
+        /// Password required to open the document.
+        [MarshalAs(UnmanagedType.LPUTF8Str)]
+        public string user_password;
+        /// Password granting full access to the document.
+        [MarshalAs(UnmanagedType.LPUTF8Str)]
+        public string owner_password;
+        /// Allow printing the document.
+        [MarshalAs(UnmanagedType.I1)]
+        public bool allow_print;
+        /// Allow copying text and graphics from the document.
+        [MarshalAs(UnmanagedType.I1)]
+        public bool allow_copy;
+        /// Allow modifying the document.
+        [MarshalAs(UnmanagedType.I1)]
+        public bool allow_modify;
+    }
+
+    [UnmanagedFunctionPointer(CallingConvention.Cdecl)]
+    [return: MarshalAs(UnmanagedType.I1)]
+    internal delegate bool WriteCallback(IntPtr data, uint len);
+
+    ///A pointer to an array of data someone else owns which may not be modified.
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct SliceCompilationOptions
+    {
+        ///Pointer to start of immutable data.
+        IntPtr data;
+        ///Number of elements.
+        ulong len;
+    }
+
+    internal partial struct SliceCompilationOptions : IEnumerable<CompilationOptions>
+    {
+        public SliceCompilationOptions(GCHandle handle, ulong count)
+        {
+            this.data = handle.AddrOfPinnedObject();
+            this.len = count;
+        }
+        public SliceCompilationOptions(IntPtr handle, ulong count)
+        {
+            this.data = handle;
+            this.len = count;
+        }
+        public CompilationOptions this[int i]
+        {
+            get
+            {
+                if (i >= Count) throw new IndexOutOfRangeException();
+                var size = Marshal.SizeOf(typeof(CompilationOptions));
+                var ptr = new IntPtr(data.ToInt64() + i * size);
+                return Marshal.PtrToStructure<CompilationOptions>(ptr);
+            }
+        }
+        public CompilationOptions[] Copied
+        {
+            get
+            {
+                var rval = new CompilationOptions[len];
+                for (var i = 0; i < (int)len; i++)
+                {
+                    rval[i] = this[i];
+                }
+                return rval;
+            }
+        }
+        public int Count => (int)len;
+        public IEnumerator<CompilationOptions> GetEnumerator()
+        {
+            for (var i = 0; i < (int)len; ++i)
+            {
+                yield return this[i];
+            }
+        }
+        IEnumerator IEnumerable.GetEnumerator()
+        {
+            return this.GetEnumerator();
+        }
+    }
+
+
     ///A pointer to an array of data someone else owns which may not be modified.
     [Serializable]
     [StructLayout(LayoutKind.Sequential)]
@@ -276,6 +499,66 @@ namespace Oicana.Interop
     }
 
 
+    ///A pointer to an array of data someone else owns which may not be modified.
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct SliceFfiInputSet
+    {
+        ///Pointer to start of immutable data.
+        IntPtr data;
+        ///Number of elements.
+        ulong len;
+    }
+
+    internal partial struct SliceFfiInputSet : IEnumerable<FfiInputSet>
+    {
+        public SliceFfiInputSet(GCHandle handle, ulong count)
+        {
+            this.data = handle.AddrOfPinnedObject();
+            this.len = count;
+        }
+        public SliceFfiInputSet(IntPtr handle, ulong count)
+        {
+            this.data = handle;
+            this.len = count;
+        }
+        public FfiInputSet this[int i]
+        {
+            get
+            {
+                if (i >= Count) throw new IndexOutOfRangeException();
+                var size = Marshal.SizeOf(typeof(FfiInputSet));
+                var ptr = new IntPtr(data.ToInt64() + i * size);
+                return Marshal.PtrToStructure<FfiInputSet>(ptr);
+            }
+        }
+        public FfiInputSet[] Copied
+        {
+            get
+            {
+                var rval = new FfiInputSet[len];
+                for (var i = 0; i < (int)len; i++)
+                {
+                    rval[i] = this[i];
+                }
+                return rval;
+            }
+        }
+        public int Count => (int)len;
+        public IEnumerator<FfiInputSet> GetEnumerator()
+        {
+            for (var i = 0; i < (int)len; ++i)
+            {
+                yield return this[i];
+            }
+        }
+        IEnumerator IEnumerable.GetEnumerator()
+        {
+            return this.GetEnumerator();
+        }
+    }
+
+
     ///A pointer to an array of data someone else owns which may not be modified.
     [Serializable]
     [StructLayout(LayoutKind.Sequential)]
@@ -336,6 +619,73 @@ namespace Oicana.Interop
     }
 
 
+    ///A pointer to an array of data someone else owns which may be modified.
+    [Serializable]
+    [StructLayout(LayoutKind.Sequential)]
+    internal partial struct SliceMutBuffer
+    {
+        ///Pointer to start of mutable data.
+        IntPtr data;
+        ///Number of elements.
+        ulong len;
+    }
+
+    internal partial struct SliceMutBuffer : IEnumerable<Buffer>
+    {
+        public SliceMutBuffer(GCHandle handle, ulong count)
+        {
+            this.data = handle.AddrOfPinnedObject();
+            this.len = count;
+        }
+        public SliceMutBuffer(IntPtr handle, ulong count)
+        {
+            this.data = handle;
+            this.len = count;
+        }
+        public Buffer this[int i]
+        {
+            get
+            {
+                if (i >= Count) throw new IndexOutOfRangeException();
+                var size = Marshal.SizeOf(typeof(Buffer));
+                var ptr = new IntPtr(data.ToInt64() + i * size);
+                return Marshal.PtrToStructure<Buffer>(ptr);
+            }
+            set
+            {
+                if (i >= Count) throw new IndexOutOfRangeException();
+                var size = Marshal.SizeOf(typeof(Buffer));
+                var ptr = new IntPtr(data.ToInt64() + i * size);
+                Marshal.StructureToPtr<Buffer>(value, ptr, false);
+            }
+        }
+        public Buffer[] Copied
+        {
+            get
+            {
+                var rval = new Buffer[len];
+                for (var i = 0; i < (int)len; i++)
+                {
+                    rval[i] = this[i];
+                }
+                return rval;
+            }
+        }
+        public int Count => (int)len;
+        public IEnumerator<Buffer> GetEnumerator()
+        {
+            for (var i = 0; i < (int)len; ++i)
+            {
+                yield return this[i];
+            }
+        }
+        IEnumerator IEnumerable.GetEnumerator()
+        {
+            return this.GetEnumerator();
+        }
+    }
+
+
 
 
     public class InteropException<T> : Exception
@@ -0,0 +1,34 @@
+using Oicana.Interop;
+
+namespace Oicana.Template;
+
+/// <summary>
+/// Result of compiling one input set of a batch
+/// </summary>
+/// <remarks>
+/// Exactly one of <see cref="Output"/> and <see cref="Error"/> is set.
+/// Dispose the output stream to free the memory of the document.
+/// </remarks>
+public class BatchResult
+{
+    internal BatchResult(Stream? output, OicanaException? error)
+    {
+        Output = output;
+        Error = error;
+    }
+
+    /// <summary>
+    /// The compiled and exported document if the compilation succeeded.
+    /// </summary>
+    public Stream? Output { get; }
+
+    /// <summary>
+    /// The error if compiling or exporting this input set failed.
+    /// </summary>
+    public OicanaException? Error { get; }
+
+    /// <summary>
+    /// Whether this input set compiled successfully.
+    /// </summary>
+    public bool IsSuccess => Output != null;
+}
@@ -17,4 +17,48 @@ public interface ITemplate
     /// <param name="compilationOptions">Options for the template compilation.</param>
     /// <exception cref="OicanaException">If the template compilation fails.</exception>
     Stream Compile(IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, CompilationOptions compilationOptions);
+
+    /// <summary>
+    /// Compile the template with the given inputs and write the export to the given stream.
+    /// </summary>
+    /// <remarks>
+    /// The export is written to the stream in chunks instead of being copied into a returned buffer.
+    /// Images, SVG, and text are written while they are encoded.
+    /// PDFs are still encoded and, if encrypted, encrypted in memory as a whole before they are written,
+    /// so for PDF only the extra copy of the finished document is avoided.
+    /// Writing to a slow stream does not block other compilations of this template.
+    /// Use this for large documents to reduce the peak memory usage, for example by passing a <see cref="FileStream"/>.
+    /// </remarks>
+    /// <param name="jsonInputs">Json inputs for the compilation.</param>
+    /// <param name="blobInputs">Blob inputs for the compilation.</param>
+    /// <param name="compilationOptions">Options for the template compilation.</param>
+    /// <param name="output">Stream to write the export to.</param>
+    /// <exception cref="OicanaException">If the template compilation fails.</exception>
+    void Compile(IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, CompilationOptions compilationOptions, Stream output);
+
+    /// <summary>
+    /// Compile the template once with the given inputs and export it to multiple formats.
+    /// </summary>
+    /// <remarks>
+    /// Use this to get, for example, a PDF together with a PNG preview of the first page without compiling twice.
+    /// All compilation options must use the same compilation mode.
+    /// </remarks>
+    /// <param name="jsonInputs">Json inputs for the compilation.</param>
+    /// <param name="blobInputs">Blob inputs for the compilation.</param>
+    /// <param name="compilationOptions">One entry per export.</param>
+    /// <exception cref="OicanaException">If the template compilation or any export fails.</exception>
+    /// <returns>One stream per compilation options in the same order.</returns>
+    IList<Stream> Compile(IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, IList<CompilationOptions> compilationOptions);
+
+    /// <summary>
+    /// Compile the template once for every input set and export each document to the specified format.
+    /// </summary>
+    /// <remarks>
+    /// The input sets are compiled in parallel. A failing input set does not affect the others.
+    /// </remarks>
+    /// <param name="inputSets">One set of inputs per compilation.</param>
+    /// <param name="compilationOptions">Options for all template compilations.</param>
+    /// <exception cref="OicanaException">If the batch could not be compiled at all.</exception>
+    /// <returns>One result per input set in the same order.</returns>
+    IList<BatchResult> CompileBatch(IList<TemplateInputSet> inputSets, CompilationOptions compilationOptions);
 }
@@ -96,6 +96,24 @@ public class Template : ITemplate, IDisposable
         return OicanaFfi.CompileTemplate(_templateId, jsonInputs, blobInputs, compilationOption);
     }
 
+    /// <inheritdoc />
+    public void Compile(IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, CompilationOptions compilationOptions, Stream output)
+    {
+        OicanaFfi.CompileTemplateToStream(_templateId, jsonInputs, blobInputs, compilationOptions, output);
+    }
+
+    /// <inheritdoc />
+    public IList<Stream> Compile(IList<TemplateJsonInput> jsonInputs, IList<TemplateBlobInput> blobInputs, IList<CompilationOptions> compilationOptions)
+    {
+        return OicanaFfi.CompileTemplateFormats(_templateId, jsonInputs, blobInputs, compilationOptions);
+    }
+
+    /// <inheritdoc />
+    public IList<BatchResult> CompileBatch(IList<TemplateInputSet> inputSets, CompilationOptions compilationOptions)
+    {
+        return OicanaFfi.CompileTemplateBatch(_templateId, inputSets, compilationOptions);
+    }
+
     /// <summary>
     /// Compile the given template once.
     /// </summary>
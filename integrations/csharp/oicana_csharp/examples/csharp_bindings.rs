@@ -20,7 +20,34 @@ fn main() -> Result<(), Error> {
         },
         oicana_csharp::my_inventory(),
     )
-    .write_file("./integrations/csharp/Oicana/Interop/OicanaFfiInternal.cs")?;
+    .write_file(BINDINGS)?;
+
+    marshal_passwords_as_utf8()
+}
+
+const BINDINGS: &str = "./integrations/csharp/Oicana/Interop/OicanaFfiInternal.cs";
+
+/// Interoptopus marshals strings with the platform default encoding.
+///
+/// PDF passwords may contain any Unicode character, so they are passed as UTF-8.
+/// Fails if a password field is not found, so a change in the generated bindings can't
+/// silently bring back the platform default encoding.
+fn marshal_passwords_as_utf8() -> Result<(), Error> {
+    let mut bindings = std::fs::read_to_string(BINDINGS)?;
+    for field in ["user_password", "owner_password"] {
+        let declaration = format!("        public string {field};");
+        if bindings.matches(&declaration).count() != 1 {
+            return Err(std::io::Error::other(format!(
+                "Expected exactly one declaration of `{field}` in the generated bindings"
+            ))
+            .into());
+        }
+        bindings = bindings.replace(
+            &declaration,
+            &format!("        [MarshalAs(UnmanagedType.LPUTF8Str)]\n{declaration}"),
+        );
+    }
+    std::fs::write(BINDINGS, bindings)?;
 
     Ok(())
 }
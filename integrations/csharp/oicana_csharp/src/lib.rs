@@ -1,26 +1,32 @@
 //! This crate defines FFI bindings for PDF templating from C#
 
 use dashmap::DashMap;
-use interoptopus::patterns::slice::FFISlice;
+use interoptopus::patterns::slice::{FFISlice, FFISliceMut};
 use interoptopus::patterns::string::AsciiPointer;
 use interoptopus::{ffi_function, ffi_type, function, Inventory, InventoryBuilder};
-use oicana_export::pdf::export_merged_pdf;
-use oicana_export::png::export_merged_png;
-use oicana_export::svg::export_merged_svg;
+use oicana_export::format::{export, export_all, write, ExportFormat};
+use oicana_export::pages::ImageExportOptions;
+use oicana_export::pdf::PdfExportOptions;
+use oicana_export::raster::{RasterFormat, ThumbnailOptions};
 use oicana_files::packed::PackedTemplate;
+use oicana_files::signature::TrustedKeys;
 use oicana_files::TemplateFiles;
 use oicana_input::input::blob::{Blob, BlobInput};
 use oicana_input::input::json::JsonInput;
 use oicana_input::{CompilationConfig, TemplateInputs};
 use oicana_world::manifest::OicanaWorldFiles;
+use oicana_world::shared::SharedTemplates;
 use oicana_world::world::OicanaWorld;
-use oicana_world::{CompiledDocument, TemplateCompilationFailure};
+use oicana_world::{evict_cache, CompiledDocument, TemplateCompilationFailure, BATCH_CHUNK_SIZE};
 use once_cell::sync::Lazy;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use serde_json::Error;
-use std::io::Cursor;
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroUsize;
 use std::slice;
 use std::sync::{Arc, Mutex};
 use typst::foundations::Bytes;
+use typst::layout::PageRanges;
 use typst::syntax::{FileId, VirtualPath};
 
 /// Register a template for the given identifier
@@ -48,12 +54,25 @@ pub unsafe extern "C" fn unsafe_register_template(
     let template = template.as_str().unwrap().to_owned();
 
     match unsafe { prepare_world(files, json_inputs, blob_inputs, compilation_options.mode) } {
-        Ok(world) => {
-            WORLD_CACHE.insert(template.clone(), world);
-            let world = WORLD_CACHE.get_mut(&template);
-            let mut world = world.unwrap();
-            let document_result = world.value_mut().compile();
-            Buffer::from_document_result(document_result, &world, compilation_options.target)
+        Ok((mut world, verified_with)) => {
+            let document_result = world.compile();
+            let buffer =
+                Buffer::from_document_result(document_result, &world, &compilation_options);
+            let keys = match TRUSTED_KEYS.lock() {
+                Ok(keys) => keys,
+                Err(error) => return Buffer::from_error(error.to_string()),
+            };
+            // The required keys might have changed since the template was verified.
+            if let Some(trusted_keys) = keys.as_ref() {
+                let verified = verified_with.is_some_and(|keys| Arc::ptr_eq(&keys, trusted_keys));
+                if !verified {
+                    if let Err(error) = world.files.verify_signature(trusted_keys) {
+                        return Buffer::from_error(format!("{error}"));
+                    }
+                }
+            }
+            WORLD_CACHE.insert(template, Arc::new(world));
+            buffer
         }
         Err(error) => error,
     }
@@ -82,9 +101,9 @@ pub unsafe extern "C" fn unsafe_compile_template_once(
     compilation_options: CompilationOptions,
 ) -> Buffer {
     match unsafe { prepare_world(files, json_inputs, blob_inputs, compilation_options.mode) } {
-        Ok(mut world) => {
+        Ok((mut world, _)) => {
             let document_result = world.compile();
-            Buffer::from_document_result(document_result, &world, compilation_options.target)
+            Buffer::from_document_result(document_result, &world, &compilation_options)
         }
         Err(error) => error,
     }
@@ -112,7 +131,7 @@ pub unsafe extern "C" fn unsafe_compile_template(
     compilation_options: CompilationOptions,
 ) -> Buffer {
     let template = template.as_str().unwrap().to_owned();
-    let world = WORLD_CACHE.get_mut(&template);
+    let world = registered_world(&template);
     let inputs = unsafe { prepare_inputs(json_inputs, blob_inputs, compilation_options.mode) };
     let inputs = match inputs {
         Err(error) => {
@@ -121,14 +140,245 @@ pub unsafe extern "C" fn unsafe_compile_template(
         Ok(inputs) => inputs,
     };
 
-    let Some(mut world) = world else {
+    let Some(world) = world else {
         return Buffer::from_error(format!("The template '{template}' is not registered"));
     };
 
-    let world = world.value_mut();
-    world.update_inputs(inputs);
-    let document_result = world.compile();
-    Buffer::from_document_result(document_result, world, compilation_options.target)
+    let document_result = compile_with_inputs(&world, inputs);
+    Buffer::from_document_result(document_result, &world, &compilation_options)
+}
+
+/// Compile the template with the given identifier once and export it into multiple formats
+///
+/// This method requires a previous successful call to [`unsafe_register_template()`].
+/// The compilation mode is taken from the first compilation options.
+/// On success, `outputs[i]` holds the export for `compilation_options[i]` and must be freed
+/// with [`unsafe_free_buffer`]. The returned buffer is empty on success and contains the error
+/// message otherwise. On error, no output buffers are written.
+///
+/// # Safety
+///
+/// The caller is responsible for ensuring that the provided
+/// `template`, `json_inputs`, `blob_inputs`, and `compilation_options` pointers are valid
+/// and non-null, and that `outputs` has the same length as `compilation_options`.
+///
+/// Additionally, the caller must ensure that the blob input buffers are not modified
+/// concurrently while this function is executing.
+#[ffi_function]
+#[no_mangle]
+pub unsafe extern "C" fn unsafe_compile_template_formats(
+    template: AsciiPointer,
+    json_inputs: FFISlice<FfiJsonInput>,
+    blob_inputs: FFISlice<FfiBlobInput>,
+    compilation_options: FFISlice<CompilationOptions>,
+    mut outputs: FFISliceMut<Buffer>,
+) -> Buffer {
+    let compilation_options = compilation_options.as_slice();
+    if compilation_options.len() != outputs.len() {
+        return Buffer::from_error(format!(
+            "Expected {} output buffers, but got {}",
+            compilation_options.len(),
+            outputs.len()
+        ));
+    }
+    let Some(mode) = compilation_options.first().map(|options| options.mode) else {
+        return Buffer::from_ok(Vec::new());
+    };
+    let export_formats = match compilation_options
+        .iter()
+        .map(CompilationOptions::export_format)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(export_formats) => export_formats,
+        Err(error) => return Buffer::from_error(error),
+    };
+
+    let template = template.as_str().unwrap().to_owned();
+    let world = registered_world(&template);
+    let inputs = unsafe { prepare_inputs(json_inputs, blob_inputs, mode) };
+    let inputs = match inputs {
+        Err(error) => {
+            return Buffer::from_error(format!("The inputs could not be prepared: {error:?}"))
+        }
+        Ok(inputs) => inputs,
+    };
+
+    let Some(world) = world else {
+        return Buffer::from_error(format!("The template '{template}' is not registered"));
+    };
+
+    let world = world.as_ref();
+    let compilation_result = match compile_with_inputs(world, inputs) {
+        Ok(compilation_result) => compilation_result,
+        Err(error) => return Buffer::from_error(format!("{error:?}")),
+    };
+
+    match export_all(&compilation_result.document, world, &export_formats) {
+        Ok(exports) => {
+            for (output, export) in outputs.as_slice_mut().iter_mut().zip(exports) {
+                *output = Buffer::from_ok(export);
+            }
+            Buffer::from_ok(Vec::new())
+        }
+        Err(error) => Buffer::from_error(format!("Error encoding compilation result: {error}")),
+    }
+}
+
+/// Compile the template with the given identifier once for every input set
+///
+/// This method requires a previous successful call to [`unsafe_register_template()`].
+/// The input sets are compiled in parallel, in chunks of [`BATCH_CHUNK_SIZE`] input sets.
+/// `outputs[i]` holds the result for `input_sets[i]`
+/// and must be freed with [`unsafe_free_buffer`]. Each output is either the exported document
+/// or an error for that input set. The returned buffer is empty on success and contains the
+/// error message if the batch could not be compiled at all. In that case, no output buffers
+/// are written.
+///
+/// # Safety
+///
+/// The caller is responsible for ensuring that the provided `template`, `input_sets`, and
+/// `compilation_options` pointers are valid and non-null, and that `outputs` has the same
+/// length as `input_sets`.
+///
+/// Additionally, the caller must ensure that the blob input buffers are not modified
+/// concurrently while this function is executing.
+#[ffi_function]
+#[no_mangle]
+pub unsafe extern "C" fn unsafe_compile_template_batch(
+    template: AsciiPointer,
+    input_sets: FFISlice<FfiInputSet>,
+    compilation_options: CompilationOptions,
+    mut outputs: FFISliceMut<Buffer>,
+) -> Buffer {
+    if input_sets.len() != outputs.len() {
+        return Buffer::from_error(format!(
+            "Expected {} output buffers, but got {}",
+            input_sets.len(),
+            outputs.len()
+        ));
+    }
+    let export_format = match compilation_options.export_format() {
+        Ok(export_format) => export_format,
+        Err(error) => return Buffer::from_error(error),
+    };
+
+    let template = template.as_str().unwrap().to_owned();
+    let Some(world) = registered_world(&template) else {
+        return Buffer::from_error(format!("The template '{template}' is not registered"));
+    };
+
+    let world = world.as_ref();
+    let target = compilation_options.target;
+    for (input_sets, outputs) in input_sets
+        .as_slice()
+        .chunks(BATCH_CHUNK_SIZE)
+        .zip(outputs.as_slice_mut().chunks_mut(BATCH_CHUNK_SIZE))
+    {
+        let inputs: Vec<_> = input_sets
+            .iter()
+            .map(|input_set| {
+                unsafe {
+                    prepare_inputs(
+                        input_set.json_inputs,
+                        input_set.blob_inputs,
+                        compilation_options.mode,
+                    )
+                }
+                .map_err(|error| format!("The inputs could not be prepared: {error:?}"))
+            })
+            .collect();
+        let results: Vec<_> = inputs
+            .into_par_iter()
+            .map(|inputs| {
+                let compilation_result = world
+                    .compile_with_inputs(inputs?)
+                    .map_err(|error| format!("{error:?}"))?;
+                export(&compilation_result.document, world, &export_format).map_err(|error| {
+                    format!("Error encoding compilation result as {target:?}: {error}")
+                })
+            })
+            .collect();
+        evict_cache();
+
+        for (output, result) in outputs.iter_mut().zip(results) {
+            *output = match result {
+                Ok(export) => Buffer::from_ok(export),
+                Err(error) => Buffer::from_error(error),
+            };
+        }
+    }
+    Buffer::from_ok(Vec::new())
+}
+
+/// Compile the template with the given identifier and stream the export into `sink`
+///
+/// This method requires a previous successful call to [`unsafe_register_template()`].
+/// The export is passed to `sink` in chunks instead of being copied into a returned buffer.
+/// Images, SVG, and text are passed on while they are encoded. PDFs are still encoded and,
+/// if requested, encrypted in memory as a whole first, so for PDF only the copy of the finished
+/// document across the FFI boundary is avoided. The pointer handed to `sink` is only valid
+/// during that call.
+/// `sink` is only called after the compilation and does not block other calls for the same
+/// template, so a slow destination doesn't hold up other compilations.
+/// If `sink` returns `false`, the export is aborted. The returned buffer is empty on success
+/// and contains the error message otherwise.
+///
+/// # Safety
+///
+/// The caller is responsible for ensuring that the provided
+/// `template`, `json_inputs`, and `blob_inputs` pointers are valid and non-null,
+/// and that `sink` is a valid function pointer.
+///
+/// Additionally, the caller must ensure that the blob input buffers are not modified
+/// concurrently while this function is executing.
+#[ffi_function]
+#[no_mangle]
+pub unsafe extern "C" fn unsafe_compile_template_to_sink(
+    template: AsciiPointer,
+    json_inputs: FFISlice<FfiJsonInput>,
+    blob_inputs: FFISlice<FfiBlobInput>,
+    compilation_options: CompilationOptions,
+    sink: WriteCallback,
+) -> Buffer {
+    let export_format = match compilation_options.export_format() {
+        Ok(export_format) => export_format,
+        Err(error) => return Buffer::from_error(error),
+    };
+    let template = template.as_str().unwrap().to_owned();
+    let world = registered_world(&template);
+    let inputs = unsafe { prepare_inputs(json_inputs, blob_inputs, compilation_options.mode) };
+    let inputs = match inputs {
+        Err(error) => {
+            return Buffer::from_error(format!("The inputs could not be prepared: {error:?}"))
+        }
+        Ok(inputs) => inputs,
+    };
+
+    let Some(world) = world else {
+        return Buffer::from_error(format!("The template '{template}' is not registered"));
+    };
+
+    let world = world.as_ref();
+    let compilation_result = match compile_with_inputs(world, inputs) {
+        Ok(compilation_result) => compilation_result,
+        Err(error) => return Buffer::from_error(format!("{error:?}")),
+    };
+
+    let mut writer = BufWriter::with_capacity(SINK_CHUNK_SIZE, SinkWriter(sink));
+    let result = write(
+        &compilation_result.document,
+        world,
+        &export_format,
+        &mut writer,
+    )
+    .and_then(|()| writer.flush().map_err(|error| error.to_string()));
+    match result {
+        Ok(()) => Buffer::from_ok(Vec::new()),
+        Err(error) => Buffer::from_error(format!(
+            "Error encoding compilation result as {:?}: {error}",
+            compilation_options.target
+        )),
+    }
 }
 
 /// Load the inputs of the given template.
@@ -139,7 +389,7 @@ pub unsafe extern "C" fn unsafe_compile_template(
 #[no_mangle]
 pub extern "C" fn inputs(template: AsciiPointer) -> Buffer {
     let template = template.as_str().unwrap();
-    let world = WORLD_CACHE.get_mut(template);
+    let world = registered_world(template);
     let Some(world) = world else {
         return Buffer::from_error(format!("The template '{template}' is not registered"));
     };
@@ -161,7 +411,7 @@ pub extern "C" fn inputs(template: AsciiPointer) -> Buffer {
 #[no_mangle]
 pub extern "C" fn get_source(template: AsciiPointer, path: AsciiPointer) -> Buffer {
     let template = template.as_str().unwrap();
-    let world = WORLD_CACHE.get_mut(template);
+    let world = registered_world(template);
     let Some(world) = world else {
         return Buffer::from_error(format!("The template '{template}' is not registered"));
     };
@@ -186,7 +436,7 @@ pub extern "C" fn get_source(template: AsciiPointer, path: AsciiPointer) -> Buff
 #[no_mangle]
 pub extern "C" fn get_file(template: AsciiPointer, path: AsciiPointer) -> Buffer {
     let template = template.as_str().unwrap();
-    let world = WORLD_CACHE.get_mut(template);
+    let world = registered_world(template);
     let Some(world) = world else {
         return Buffer::from_error(format!("The template '{template}' is not registered"));
     };
@@ -236,12 +486,73 @@ pub unsafe extern "C" fn unsafe_free_buffer(buffer: Buffer) {
     }
 }
 
+/// Register a font for all templates.
+///
+/// Registered templates are updated to use the font. Templates registered afterwards can use it
+/// as well. On success, the buffer contains the families of the registered fonts separated by
+/// line breaks.
+///
+/// # Safety
+///
+/// The caller is responsible for ensuring that the provided `font` pointer is valid and non-null,
+/// and that the data is properly aligned and initialized.
+#[ffi_function]
+#[no_mangle]
+pub unsafe extern "C" fn unsafe_register_font(font: Buffer) -> Buffer {
+    let data = unsafe { slice::from_raw_parts::<u8>(font.data, font.len as usize) }.to_vec();
+    let families = SHARED_TEMPLATES.register_font(Bytes::new(data));
+    if families.is_empty() {
+        return Buffer::from_error("The data does not contain a supported font".to_owned());
+    }
+    for mut world in WORLD_CACHE.iter_mut() {
+        let fonts = SHARED_TEMPLATES.fonts(&world.files);
+        Arc::make_mut(world.value_mut()).set_fonts(fonts);
+    }
+
+    Buffer::from_ok(families.join("\n").into_bytes())
+}
+
+/// Only accept templates signed by one of the given public keys.
+///
+/// `public_keys` contains one or more PEM encoded Ed25519 public keys. Afterwards, registering or
+/// compiling unsigned templates and templates modified after signing fails. Templates that are
+/// already registered and not signed by one of the keys are unregistered. On success, the buffer
+/// contains the identifiers of the unregistered templates separated by line breaks.
+#[ffi_function]
+#[no_mangle]
+pub extern "C" fn require_signed_templates(public_keys: AsciiPointer) -> Buffer {
+    let Some(public_keys) = public_keys.as_str().ok() else {
+        return Buffer::from_error("The public keys are not valid ASCII".to_owned());
+    };
+    let trusted_keys = match TrustedKeys::from_pem(public_keys) {
+        Ok(trusted_keys) => Arc::new(trusted_keys),
+        Err(error) => return Buffer::from_error(format!("{error}")),
+    };
+
+    // Registrations insert their world while holding the lock, so none is missed here.
+    let mut keys = match TRUSTED_KEYS.lock() {
+        Ok(keys) => keys,
+        Err(error) => return Buffer::from_error(error.to_string()),
+    };
+    let unsigned: Vec<String> = WORLD_CACHE
+        .iter()
+        .filter(|world| world.files.verify_signature(&trusted_keys).is_err())
+        .map(|world| world.key().clone())
+        .collect();
+    for template in &unsigned {
+        WORLD_CACHE.remove(template);
+    }
+    *keys = Some(trusted_keys);
+
+    Buffer::from_ok(unsigned.join("\n").into_bytes())
+}
+
 /// Configure Oicana.
 #[ffi_function]
 #[no_mangle]
 pub extern "C" fn configure(config: Config) -> Buffer {
     for mut world in WORLD_CACHE.iter_mut() {
-        world.color = config.color.into();
+        Arc::make_mut(world.value_mut()).color = config.color.into();
     }
 
     match CONFIGURATION.lock() {
@@ -253,19 +564,51 @@ pub extern "C" fn configure(config: Config) -> Buffer {
     }
 }
 
+/// Get a registered template without holding on to the cache entry.
+///
+/// Compilations run on the returned world, so they don't block other calls for the same template.
+fn registered_world(template: &str) -> Option<Arc<TemplateWorld>> {
+    WORLD_CACHE.get(template).map(|world| Arc::clone(&world))
+}
+
+/// Compile a registered template with the given inputs.
+///
+/// The world is shared with concurrent compilations, so the inputs are not stored in it.
+fn compile_with_inputs(
+    world: &TemplateWorld,
+    inputs: TemplateInputs,
+) -> Result<CompiledDocument, TemplateCompilationFailure> {
+    // Like `OicanaWorld::compile`, to prevent https://github.com/typst/typst/issues/6832
+    evict_cache();
+    world.compile_with_inputs(inputs)
+}
+
+/// Prepare the world of a template and verify it against the required keys.
+///
+/// Also returns the keys the template was verified with, if signed templates are required.
 unsafe fn prepare_world(
     files: Buffer,
     json_inputs: FFISlice<FfiJsonInput>,
     blob_inputs: FFISlice<FfiBlobInput>,
     compilation_mode: CompilationMode,
-) -> Result<OicanaWorld<PackedTemplate>, Buffer> {
-    let files = unsafe {
-        PackedTemplate::new(Cursor::new(slice::from_raw_parts::<u8>(
-            files.data,
-            files.len as usize,
-        )))
+) -> Result<(TemplateWorld, Option<Arc<TrustedKeys>>), Buffer> {
+    let template = unsafe {
+        SHARED_TEMPLATES.load(slice::from_raw_parts::<u8>(files.data, files.len as usize))
     };
-    let manifest = match files.manifest() {
+    let template = match template {
+        Ok(template) => template,
+        Err(error) => return Err(Buffer::from_error(format!("{error}"))),
+    };
+    let trusted_keys = match TRUSTED_KEYS.lock() {
+        Ok(keys) => keys.clone(),
+        Err(error) => return Err(Buffer::from_error(error.to_string())),
+    };
+    if let Some(trusted_keys) = &trusted_keys {
+        if let Err(error) = template.files.verify_signature(trusted_keys) {
+            return Err(Buffer::from_error(format!("{error}")));
+        }
+    }
+    let manifest = match template.files.manifest() {
         Ok(manifest) => manifest,
         Err(error) => return Err(Buffer::from_error(format!("{error}"))),
     };
@@ -281,11 +624,11 @@ unsafe fn prepare_world(
 
     let color = get_diagnostic_color();
 
-    OicanaWorld::new(files, inputs, manifest)
+    OicanaWorld::with_fonts(template.files, inputs, manifest, template.fonts)
         .map_err(|error| Buffer::from_error(format!("{error}")))
         .map(|mut world| {
             world.color = color;
-            world
+            (world, trusted_keys)
         })
 }
 
@@ -333,34 +676,59 @@ impl Buffer {
 
     fn from_document_result(
         document_result: Result<CompiledDocument, TemplateCompilationFailure>,
-        world: &OicanaWorld<PackedTemplate>,
-        format: CompilationTarget,
+        world: &TemplateWorld,
+        options: &CompilationOptions,
     ) -> Self {
-        match document_result {
-            Ok(compilation_result) => match format {
-                CompilationTarget::Pdf => {
-                    match export_merged_pdf(&compilation_result.document, world) {
-                        Err(error) => Buffer::from_error(format!(
-                            "Error encoding compilation result as PDF: {error:?}"
-                        )),
-                        Ok(pdf) => Buffer::from_ok(pdf),
-                    }
-                }
-                CompilationTarget::Png => {
-                    match export_merged_png(&compilation_result.document, 1.0) {
-                        Err(error) => Buffer::from_error(format!(
-                            "Error encoding compilation result as PNG: {error:?}"
-                        )),
-                        Ok(png) => Buffer::from_ok(png),
-                    }
-                }
-                CompilationTarget::Svg => {
-                    Buffer::from_ok(export_merged_svg(&compilation_result.document))
-                }
-            },
-            Err(error) => Buffer::from_error(format!("{error:?}")),
+        let compilation_result = match document_result {
+            Ok(compilation_result) => compilation_result,
+            Err(error) => return Buffer::from_error(format!("{error:?}")),
+        };
+        let export_format = match options.export_format() {
+            Ok(export_format) => export_format,
+            Err(error) => return Buffer::from_error(error),
+        };
+
+        match export(&compilation_result.document, world, &export_format) {
+            Err(error) => Buffer::from_error(format!(
+                "Error encoding compilation result as {:?}: {error}",
+                options.target
+            )),
+            Ok(output) => Buffer::from_ok(output),
+        }
+    }
+}
+
+pub use sink::WriteCallback;
+
+mod sink {
+    // The callback macro does not support doc comments.
+    #![allow(missing_docs)]
+
+    use interoptopus::callback;
+
+    // Receives a chunk of an export. Returning `false` aborts the export.
+    callback!(WriteCallback(data: *const u8, len: u32) -> bool);
+}
+
+/// Size of the chunks passed to a [`WriteCallback`].
+const SINK_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Passes everything written to it on to a callback of the caller.
+struct SinkWriter(WriteCallback);
+
+impl Write for SinkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(u32::MAX as usize);
+        match self.0.call_if_some(buf.as_ptr(), len as u32) {
+            Some(true) => Ok(len),
+            Some(false) => Err(io::Error::other("The sink did not accept the data")),
+            None => Err(io::Error::other("No sink was given")),
         }
     }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 /// A collection of string pairs representing JSON inputs
@@ -387,6 +755,16 @@ pub struct FfiBlobInput<'a> {
     pub meta: AsciiPointer<'a>,
 }
 
+/// One set of inputs for a batch compilation
+#[ffi_type]
+#[repr(C)]
+pub struct FfiInputSet<'a> {
+    /// Blob inputs of this input set.
+    pub blob_inputs: FFISlice<'a, FfiBlobInput<'a>>,
+    /// JSON inputs of this input set.
+    pub json_inputs: FFISlice<'a, FfiJsonInput<'a>>,
+}
+
 /// Formats that an Oicana template can be compiled into.
 #[ffi_type]
 #[repr(C)]
@@ -402,6 +780,14 @@ pub enum CompilationTarget {
     Png,
     /// Render the template as SVG file.
     Svg,
+    /// Render the template into a lossy JPEG image.
+    Jpeg,
+    /// Render the template into a lossless WebP image.
+    WebP,
+    /// Render the first page as JPEG thumbnail fitting into a bounding box.
+    Thumbnail,
+    /// Extract the plain text of all pages as UTF-8, separated by form feeds.
+    Text,
 }
 
 /// The mode of compilation
@@ -419,8 +805,8 @@ pub enum CompilationMode {
 /// Options for compiling the template
 #[ffi_type]
 #[repr(C)]
-#[derive(Copy, Clone, Debug)]
-pub struct CompilationOptions {
+#[derive(Debug)]
+pub struct CompilationOptions<'a> {
     /// Formats that an Oicana template can be compiled into.
     pub target: CompilationTarget,
     /// The mode of compilation
@@ -428,6 +814,130 @@ pub struct CompilationOptions {
     /// Pixels per pt
     /// Only used for PNG export
     pub px_per_pt: f32,
+    /// Password protection
+    /// Only used for PDF export
+    pub pdf_encryption: PdfEncryption<'a>,
+    /// Pages to export
+    /// Only used for PNG, JPEG, WebP, and SVG export
+    pub pages: PageRange,
+    /// Quality between 1 and 100
+    /// Only used for JPEG and thumbnail export, WebP images are lossless and have no quality setting
+    pub quality: u8,
+    /// Maximum width of the thumbnail in pixels
+    /// Only used for thumbnail export
+    pub thumbnail_width: u32,
+    /// Maximum height of the thumbnail in pixels
+    /// Only used for thumbnail export
+    pub thumbnail_height: u32,
+}
+
+impl CompilationOptions<'_> {
+    fn export_format(&self) -> Result<ExportFormat, String> {
+        Ok(match self.target {
+            CompilationTarget::Pdf => ExportFormat::Pdf(self.pdf_export_options()?),
+            CompilationTarget::Png => ExportFormat::Png(self.image_export_options()),
+            CompilationTarget::Svg => ExportFormat::Svg(self.image_export_options()),
+            CompilationTarget::Jpeg => ExportFormat::Raster(
+                self.image_export_options(),
+                RasterFormat::Jpeg {
+                    quality: self.quality,
+                },
+            ),
+            CompilationTarget::WebP => {
+                ExportFormat::Raster(self.image_export_options(), RasterFormat::WebP)
+            }
+            CompilationTarget::Thumbnail => ExportFormat::Thumbnail(ThumbnailOptions {
+                max_width: self.thumbnail_width,
+                max_height: self.thumbnail_height,
+                format: RasterFormat::Jpeg {
+                    quality: self.quality,
+                },
+            }),
+            CompilationTarget::Text => ExportFormat::Text,
+        })
+    }
+
+    fn image_export_options(&self) -> ImageExportOptions {
+        ImageExportOptions {
+            pages: Some(self.pages.into()),
+            pixels_per_pt: self.px_per_pt,
+            ..ImageExportOptions::default()
+        }
+    }
+
+    fn pdf_export_options(&self) -> Result<PdfExportOptions, String> {
+        let encryption = &self.pdf_encryption;
+        if !encryption.enabled {
+            return Ok(PdfExportOptions::default());
+        }
+        let owner_password = encryption
+            .owner_password
+            .as_str()
+            .map_err(|error| format!("Invalid owner password for PDF encryption: {error:?}"))?;
+        if owner_password.is_empty() {
+            return Err("The owner password for PDF encryption must not be empty".to_owned());
+        }
+        // An empty user password allows opening the document without a password.
+        let user_password = encryption
+            .user_password
+            .as_str()
+            .map_err(|error| format!("Invalid user password for PDF encryption: {error:?}"))?;
+
+        Ok(PdfExportOptions {
+            encryption: Some(oicana_export::pdf::PdfEncryption {
+                user_password: user_password.to_owned(),
+                owner_password: owner_password.to_owned(),
+                permissions: oicana_export::pdf::PdfPermissions {
+                    print: encryption.allow_print,
+                    copy: encryption.allow_copy,
+                    modify: encryption.allow_modify,
+                },
+            }),
+        })
+    }
+}
+
+/// Password protection for PDF exports.
+///
+/// Encrypted PDFs use AES-256 and are not PDF/A conform.
+#[ffi_type]
+#[repr(C)]
+#[derive(Debug)]
+pub struct PdfEncryption<'a> {
+    /// Whether the exported PDF should be encrypted.
+    pub enabled: bool,
+    /// Password required to open the document.
+    pub user_password: AsciiPointer<'a>,
+    /// Password granting full access to the document.
+    pub owner_password: AsciiPointer<'a>,
+    /// Allow printing the document.
+    pub allow_print: bool,
+    /// Allow copying text and graphics from the document.
+    pub allow_copy: bool,
+    /// Allow modifying the document.
+    pub allow_modify: bool,
+}
+
+/// One-indexed, inclusive range of pages.
+///
+/// A bound of `0` leaves the range open on that side.
+#[ffi_type]
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PageRange {
+    /// First page to export.
+    pub first: u32,
+    /// Last page to export.
+    pub last: u32,
+}
+
+impl From<PageRange> for PageRanges {
+    fn from(value: PageRange) -> Self {
+        let first = NonZeroUsize::new(value.first as usize);
+        let last = NonZeroUsize::new(value.last as usize);
+
+        PageRanges::new(vec![first..=last])
+    }
 }
 
 unsafe fn prepare_inputs(
@@ -504,13 +1014,25 @@ static CONFIGURATION: Lazy<Arc<Mutex<Config>>> = Lazy::new(|| {
     }))
 });
 
-static WORLD_CACHE: Lazy<DashMap<String, OicanaWorld<PackedTemplate>>> = Lazy::new(DashMap::new);
+/// Public keys that templates must be signed with, if signed templates are required.
+static TRUSTED_KEYS: Lazy<Mutex<Option<Arc<TrustedKeys>>>> = Lazy::new(|| Mutex::new(None));
+
+/// Files and fonts of registered templates, shared between identical template archives.
+static SHARED_TEMPLATES: Lazy<SharedTemplates> = Lazy::new(SharedTemplates::new);
+
+/// World of a registered template
+type TemplateWorld = OicanaWorld<Arc<PackedTemplate>>;
+
+static WORLD_CACHE: Lazy<DashMap<String, Arc<TemplateWorld>>> = Lazy::new(DashMap::new);
 
 /// List methods for auto generated bindings
 pub fn my_inventory() -> Inventory {
     InventoryBuilder::new()
         .register(function!(unsafe_compile_template))
         .register(function!(unsafe_compile_template_once))
+        .register(function!(unsafe_compile_template_formats))
+        .register(function!(unsafe_compile_template_batch))
+        .register(function!(unsafe_compile_template_to_sink))
         .register(function!(unsafe_register_template))
         .register(function!(inputs))
         .register(function!(get_source))
@@ -518,5 +1040,7 @@ pub fn my_inventory() -> Inventory {
         .register(function!(unsafe_free_buffer))
         .register(function!(unregister_template))
         .register(function!(configure))
+        .register(function!(require_signed_templates))
+        .register(function!(unsafe_register_font))
         .inventory()
 }
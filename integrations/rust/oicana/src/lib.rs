@@ -6,15 +6,22 @@
 
 use std::io::{Read, Seek};
 
-use oicana_files::{packed::PackedTemplate, TemplateFiles};
+use oicana_export::format::{export_all, ExportFormat};
+use oicana_files::{
+    packed::{LazyPackedTemplate, PackedTemplate, PackedTemplateError},
+    signature::TrustedKeys,
+    TemplateFiles,
+};
 use oicana_input::TemplateInputs;
 use oicana_template::manifest::TemplateManifest;
 use oicana_world::{
     diagnostics::TemplateDiagnostics,
+    evict_cache,
     manifest::{OicanaWorldFiles, OicanaWorldManifestError},
     world::{OicanaWorld, WorldCreationError},
-    CompiledDocument, TemplateCompilationFailure,
+    CompiledDocument, TemplateCompilationFailure, BATCH_CHUNK_SIZE,
 };
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use thiserror::Error;
 use typst::{
     diag::{FileResult, SourceDiagnostic},
@@ -23,6 +30,11 @@ use typst::{
     syntax::{FileId, Source},
 };
 
+/// Options for exporting compiled documents.
+pub use oicana_export as export;
+/// Signing template archives and verifying their signatures.
+pub use oicana_files::signature;
+
 /// Support for native Oicana templates.
 /// Native templates are not packed. They are a Typst project in a native file system.
 #[cfg(feature = "native")]
@@ -36,7 +48,58 @@ pub struct Template<F: TemplateFiles> {
 impl Template<PackedTemplate> {
     /// Initialize the given template
     pub fn init<R: Read + Seek>(template: R) -> Result<Self, TemplateInitializationError> {
-        let files = PackedTemplate::new(template);
+        let files = PackedTemplate::try_new(template)?;
+        let manifest = files.manifest()?;
+
+        let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
+
+        Ok(Template { world })
+    }
+
+    /// Initialize the given template in strict mode
+    ///
+    /// The template must be signed by one of the trusted keys. Unsigned templates and templates
+    /// that were modified after signing are rejected.
+    pub fn init_signed<R: Read + Seek>(
+        template: R,
+        trusted_keys: &TrustedKeys,
+    ) -> Result<Self, TemplateInitializationError> {
+        let files = PackedTemplate::try_new_signed(template, trusted_keys)?;
+        let manifest = files.manifest()?;
+
+        let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
+
+        Ok(Template { world })
+    }
+}
+
+impl<R: Read + Seek + Send> Template<LazyPackedTemplate<R>> {
+    /// Initialize the given template without decompressing all of its files
+    ///
+    /// Files are decompressed when the compilation accesses them. At most `memory_limit` bytes of
+    /// decompressed files are kept in memory.
+    pub fn init_lazy(
+        template: R,
+        memory_limit: usize,
+    ) -> Result<Self, TemplateInitializationError> {
+        let files = LazyPackedTemplate::try_new(template, memory_limit)?;
+        let manifest = files.manifest()?;
+
+        let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
+
+        Ok(Template { world })
+    }
+
+    /// Initialize the given template without decompressing all of its files in strict mode
+    ///
+    /// See [`Template::init_lazy`] and [`Template::init_signed`]. All files are decompressed once
+    /// to verify the signature. Afterwards, they are decompressed on demand again.
+    pub fn init_lazy_signed(
+        template: R,
+        memory_limit: usize,
+        trusted_keys: &TrustedKeys,
+    ) -> Result<Self, TemplateInitializationError> {
+        let files = LazyPackedTemplate::try_new_signed(template, memory_limit, trusted_keys)?;
         let manifest = files.manifest()?;
 
         let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
@@ -55,6 +118,108 @@ impl<Files: TemplateFiles> Template<Files> {
         self.world.compile()
     }
 
+    /// Compile the template with given inputs and export the document into all given formats
+    ///
+    /// The template is compiled only once, no matter how many formats are requested.
+    /// The outputs are in the same order as the formats.
+    pub fn compile_and_export(
+        &mut self,
+        inputs: TemplateInputs,
+        formats: &[ExportFormat],
+    ) -> Result<ExportedDocument, TemplateExportError> {
+        let compiled = self.compile(inputs)?;
+        let outputs = export_all(&compiled.document, &self.world, formats)
+            .map_err(TemplateExportError::ExportError)?;
+
+        Ok(ExportedDocument {
+            outputs,
+            warnings: compiled.warnings,
+        })
+    }
+
+    /// Compile the template once for every set of inputs
+    ///
+    /// The input sets are compiled in parallel, in chunks of [`BATCH_CHUNK_SIZE`]. Every input set
+    /// gets its own result, so a failing input set does not affect the others.
+    /// `on_result` is called with the index of the input set and its result in the order of the
+    /// input sets. Only the results of one chunk are kept in memory at a time.
+    pub fn compile_batch(
+        &self,
+        inputs: impl IntoIterator<Item = TemplateInputs>,
+        on_result: impl FnMut(usize, Result<CompiledDocument, TemplateCompilationFailure>),
+    ) {
+        self.for_each_chunk(
+            inputs,
+            |inputs| self.world.compile_with_inputs(inputs),
+            on_result,
+        );
+    }
+
+    /// Compile the template once for every set of inputs and export each document into all given formats
+    ///
+    /// See [`Self::compile_batch`] and [`Self::compile_and_export`].
+    pub fn compile_and_export_batch(
+        &self,
+        inputs: impl IntoIterator<Item = TemplateInputs>,
+        formats: &[ExportFormat],
+        on_result: impl FnMut(usize, Result<ExportedDocument, TemplateExportError>),
+    ) {
+        self.for_each_chunk(
+            inputs,
+            |inputs| {
+                let compiled = self.world.compile_with_inputs(inputs)?;
+                let outputs = export_all(&compiled.document, &self.world, formats)
+                    .map_err(TemplateExportError::ExportError)?;
+
+                Ok(ExportedDocument {
+                    outputs,
+                    warnings: compiled.warnings,
+                })
+            },
+            on_result,
+        );
+    }
+
+    /// Process the inputs in parallel chunks and evict the compilation cache after every chunk.
+    fn for_each_chunk<T: Send>(
+        &self,
+        inputs: impl IntoIterator<Item = TemplateInputs>,
+        process: impl Fn(TemplateInputs) -> T + Sync + Send,
+        mut on_result: impl FnMut(usize, T),
+    ) {
+        let mut inputs = inputs.into_iter();
+        let mut index = 0;
+        loop {
+            let chunk: Vec<_> = inputs.by_ref().take(BATCH_CHUNK_SIZE).collect();
+            if chunk.is_empty() {
+                break;
+            }
+            let results: Vec<_> = chunk.into_par_iter().map(&process).collect();
+            evict_cache();
+
+            for result in results {
+                on_result(index, result);
+                index += 1;
+            }
+        }
+    }
+
+    /// Add a font report to every compiled document
+    ///
+    /// The report lists the used fonts, characters without a glyph in the requested font families,
+    /// and characters without a glyph in any available font.
+    pub fn report_fonts(&mut self, enabled: bool) {
+        self.world.report_fonts = enabled;
+    }
+
+    /// Set the font families the document is expected to be set in
+    ///
+    /// Font reports list characters rendered with a font outside of these families.
+    /// By default, the families of the fonts in the template are expected.
+    pub fn requested_font_families(&mut self, families: Vec<String>) {
+        self.world.requested_font_families = families;
+    }
+
     /// Get the manifest of the template
     pub fn manifest(&self) -> &TemplateManifest {
         self.world.manifest()
@@ -80,6 +245,10 @@ impl<Files: TemplateFiles> TemplateDiagnostics for Template<Files> {
 /// An error occurred while initiating the template
 #[derive(Error, Debug)]
 pub enum TemplateInitializationError {
+    /// The packed template could not be read
+    #[error("Invalid template archive: {0}")]
+    PackedTemplateError(#[from] PackedTemplateError),
+
     /// An error concerning the template manifest
     #[error("Manifest error: {0}")]
     ManifestError(#[from] OicanaWorldManifestError),
@@ -92,3 +261,23 @@ pub enum TemplateInitializationError {
     #[error("Failed to find the data directory for Typst packages on the System")]
     PackageDirectoryNotFound,
 }
+
+/// A compiled document exported into one or multiple formats.
+pub struct ExportedDocument {
+    /// One output per requested format, in the same order.
+    pub outputs: Vec<Vec<u8>>,
+    /// Warnings from the compilation.
+    pub warnings: Option<String>,
+}
+
+/// An error occurred while compiling and exporting the template
+#[derive(Error, Debug)]
+pub enum TemplateExportError {
+    /// The template failed to compile
+    #[error(transparent)]
+    CompilationError(#[from] TemplateCompilationFailure),
+
+    /// The compiled document could not be exported
+    #[error("Export failed: {0}")]
+    ExportError(String),
+}
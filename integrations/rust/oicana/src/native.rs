@@ -1,9 +1,10 @@
 use std::path::Path;
+use std::sync::Arc;
 
-use oicana_files::native::{package_data_dir, NativeTemplate};
+use oicana_files::native::{package_data_dir, NativeTemplate, PackageRegistry, PackageResolver};
 use oicana_input::TemplateInputs;
 use oicana_template::manifest::TemplateManifest;
-use oicana_world::{manifest::OicanaWorldFiles, world::OicanaWorld};
+use oicana_world::{fonts::FontCollection, manifest::OicanaWorldFiles, world::OicanaWorld};
 
 use crate::{Template, TemplateInitializationError};
 
@@ -13,6 +14,7 @@ impl Template<NativeTemplate> {
         let files = NativeTemplate::new(
             path,
             package_data_dir().ok_or(TemplateInitializationError::PackageDirectoryNotFound)?,
+            PackageRegistry::Default,
         );
         let manifest = files.manifest()?;
 
@@ -21,13 +23,53 @@ impl Template<NativeTemplate> {
         Ok(Template { world })
     }
 
+    /// Initialize the given template with fonts from the file system
+    ///
+    /// Fonts in `font_paths` and, if `include_system_fonts` is set, fonts installed on the system
+    /// are available to the template. Fonts of the template itself still have priority.
+    /// Missing packages are downloaded from the given registry.
+    pub fn init_with_system_fonts<P: AsRef<Path>>(
+        path: &Path,
+        font_paths: &[P],
+        include_system_fonts: bool,
+        registry: PackageRegistry,
+    ) -> Result<Self, TemplateInitializationError> {
+        let files = NativeTemplate::new(
+            path,
+            package_data_dir().ok_or(TemplateInitializationError::PackageDirectoryNotFound)?,
+            registry,
+        );
+        let manifest = files.manifest()?;
+        let mut fonts = FontCollection::new();
+        fonts.collect_with_system_fonts(&files, font_paths, include_system_fonts);
+
+        let world =
+            OicanaWorld::with_fonts(files, TemplateInputs::new(), manifest, Arc::new(fonts))?;
+
+        Ok(Template { world })
+    }
+
+    /// Initialize the given template, resolving packages with the given resolver
+    pub fn init_with_resolver(
+        path: &Path,
+        resolver: impl PackageResolver + 'static,
+    ) -> Result<Self, TemplateInitializationError> {
+        let files = NativeTemplate::with_resolver(path, resolver);
+        let manifest = files.manifest()?;
+
+        let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
+
+        Ok(Template { world })
+    }
+
     /// Create a native template from all required parts
     pub fn from(
         template_root: &Path,
         packages: &Path,
+        registry: PackageRegistry,
         manifest: TemplateManifest,
     ) -> Result<Self, TemplateInitializationError> {
-        let files = NativeTemplate::new(template_root, packages.to_path_buf());
+        let files = NativeTemplate::new(template_root, packages.to_path_buf(), registry);
         let world = OicanaWorld::new(files, TemplateInputs::new(), manifest)?;
 
         Ok(Template { world })
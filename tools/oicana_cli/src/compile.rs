@@ -1,15 +1,26 @@
-use crate::compile::export::{export_image, export_pdf, ExportFormat, ImageExportFormat};
+use crate::compile::batch::compile_batch;
+use crate::compile::export::{
+    export_image, export_pdf, export_split_pdf_files, export_text_file, export_thumbnail_image,
+    parse_page_range, parse_size, ExportFormat, ImageExportFormat, PdfPermission,
+};
+use crate::registry::RegistryArgs;
 use anyhow::{Context, Ok};
+use clap::builder::NonEmptyStringValueParser;
 use clap::Args;
 use log::{info, warn};
 use oicana::Template;
+use oicana_export::pages::ImageExportOptions;
+use oicana_export::pdf::{PdfEncryption, PdfExportOptions, PdfPermissions};
+use oicana_export::raster::{RasterFormat, ThumbnailOptions};
 use oicana_files::native::NativeTemplate;
 use oicana_input::input::blob::BlobInput;
 use oicana_input::input::json::JsonInput;
 use oicana_input::{CompilationConfig, TemplateInputs};
 use std::fs::{read, read_to_string};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use typst::layout::{PageRange, PageRanges};
 
+mod batch;
 mod export;
 
 #[rustfmt::skip]
@@ -18,6 +29,13 @@ pub const COMPILE_AFTER_HELP: &str = color_print::cstr!("\
   oicana compile
   oicana compile templates/invoice
   oicana compile -j test=inputs/input1.json -j foo=bar.json -b logo=company.png
+  oicana compile --owner-password secret --user-password 1234 --allow print
+  oicana compile --owner-password secret --allow none
+  oicana compile -f png --pages 1,3-4 --ppi 300
+  oicana compile -f thumbnail --thumbnail-size 200x200 --quality 70
+  oicana compile --batch inputs.ndjson --output-dir out/ -b logo=company.png
+  oicana compile --split recipient --output-dir letters/
+  oicana compile --font-path fonts/ --system-fonts
 ");
 
 #[derive(Debug, Args)]
@@ -46,35 +64,239 @@ pub struct CompileArgs {
     blob: Vec<String>,
     #[arg(short, long, help = "Compile the template in development mode")]
     development: bool,
+    #[arg(
+        long,
+        help = "Encrypt the PDF with this owner password. Encrypted PDFs are not PDF/A conform.",
+        value_parser = NonEmptyStringValueParser::new()
+    )]
+    owner_password: Option<String>,
+    #[arg(
+        long,
+        help = "Password required to open the encrypted PDF",
+        requires = "owner_password"
+    )]
+    user_password: Option<String>,
+    #[arg(
+        long,
+        help = "Permissions for users of the encrypted PDF. Use 'none' to grant no permissions.",
+        value_delimiter = ',',
+        default_values = ["print", "copy", "modify"],
+        requires = "owner_password"
+    )]
+    allow: Vec<PdfPermission>,
+    #[arg(
+        long,
+        help = "Pages to export as images, e.g. 1,3-4 or 2-. Exports all pages by default.",
+        value_name = "PAGES",
+        value_delimiter = ',',
+        value_parser = parse_page_range
+    )]
+    pages: Vec<PageRange>,
+    #[arg(
+        long,
+        help = "Resolution of PNG, JPEG, and WebP images in pixels per inch",
+        default_value_t = 144.0
+    )]
+    ppi: f32,
+    #[arg(
+        long,
+        help = "Quality of JPEG images and thumbnails between 1 and 100 [default: 85]. WebP images are lossless and have no quality setting.",
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    quality: Option<u8>,
+    #[arg(
+        long,
+        help = "Bounding box of the thumbnail in pixels",
+        value_name = "WIDTHxHEIGHT",
+        default_value = "256x256",
+        value_parser = parse_size
+    )]
+    thumbnail_size: (u32, u32),
+    #[arg(
+        long,
+        help = "Compile one document per line of this NDJSON file. Each line is a JSON object with input keys and values. Pages are merged into one file per document.",
+        value_name = "FILE"
+    )]
+    batch: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Directory for the documents of a batch or a split PDF [default: output]"
+    )]
+    output_dir: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Split the PDF into one file per element with this label. A string in a labelled metadata element names the file.",
+        value_name = "LABEL",
+        conflicts_with = "batch"
+    )]
+    split: Option<String>,
+    #[arg(
+        long = "font-path",
+        help = "Additional directory to search for fonts. Can be given multiple times.",
+        value_name = "DIR"
+    )]
+    font_paths: Vec<PathBuf>,
+    #[arg(
+        long,
+        help = "Also use fonts installed on the system. Without it, only fonts of the template, the font paths, and embedded fonts are used, like for packed templates."
+    )]
+    system_fonts: bool,
+    #[arg(
+        long,
+        help = "Print the fonts used in the document and characters without a glyph in any font",
+        conflicts_with = "batch"
+    )]
+    font_report: bool,
+    #[arg(
+        long = "font-family",
+        help = "Font family the document is expected to be set in. The font report lists characters rendered with other fonts. Can be given multiple times. Defaults to the families of the fonts in the template.",
+        value_name = "FAMILY",
+        requires = "font_report"
+    )]
+    font_families: Vec<String>,
+    #[clap(flatten)]
+    registry: RegistryArgs,
 }
 
 pub fn compile(args: CompileArgs) -> anyhow::Result<()> {
+    if args.quality.is_some()
+        && !matches!(args.format, ExportFormat::Jpeg | ExportFormat::Thumbnail)
+    {
+        anyhow::bail!("--quality is only supported for JPEG images and thumbnails");
+    }
+    if args.allow.contains(&PdfPermission::None) && args.allow.len() > 1 {
+        anyhow::bail!("--allow none can't be combined with other permissions");
+    }
+    if args.split.is_some() && !matches!(args.format, ExportFormat::Pdf) {
+        anyhow::bail!("--split is only supported for PDF documents");
+    }
     let inputs = build_inputs(&args)?;
 
     let path = match args.template {
         None => Path::new("."),
         Some(ref template) => Path::new(template),
     };
-    let mut template = Template::<NativeTemplate>::init(path)?;
+    let mut template = Template::<NativeTemplate>::init_with_system_fonts(
+        path,
+        &args.font_paths,
+        args.system_fonts,
+        args.registry.registry(),
+    )?;
     let name: String = template.manifest().package.name.to_string();
     info!("Compiling template '{name}'.");
 
+    if let Some(ref batch) = args.batch {
+        return compile_batch(&args, &template, inputs, batch);
+    }
+
+    template.report_fonts(args.font_report);
+    template.requested_font_families(args.font_families.clone());
     let result = template.compile(inputs)?;
 
     let document = result.document;
     if let Some(warnings) = result.warnings {
         println!("{warnings}");
     }
+    if let Some(report) = result.fonts {
+        print!("{report}");
+        if !report.fallback_glyphs.is_empty() {
+            warn!(
+                "{} characters have no glyph in the requested font families.",
+                report.fallback_glyphs.len()
+            );
+        }
+        if !report.missing_glyphs.is_empty() {
+            warn!(
+                "{} characters have no glyph in any available font.",
+                report.missing_glyphs.len()
+            );
+        }
+    }
 
     match args.format {
-        ExportFormat::Pdf => export_pdf(&document, &name, &template)?,
-        ExportFormat::Png => export_image(&document, ImageExportFormat::Png, &name)?,
-        ExportFormat::Svg => export_image(&document, ImageExportFormat::Svg, &name)?,
+        ExportFormat::Pdf => match args.split {
+            Some(ref label) => export_split_pdf_files(
+                &document,
+                &template,
+                label,
+                &pdf_options(&args),
+                args.output_dir.as_deref().unwrap_or(Path::new("output")),
+            )?,
+            None => export_pdf(&document, &name, &template, &pdf_options(&args))?,
+        },
+        ExportFormat::Png => export_image(
+            &document,
+            ImageExportFormat::Png,
+            &name,
+            &image_options(&args),
+        )?,
+        ExportFormat::Jpeg => export_image(
+            &document,
+            ImageExportFormat::Jpeg {
+                quality: quality(&args),
+            },
+            &name,
+            &image_options(&args),
+        )?,
+        ExportFormat::Webp => export_image(
+            &document,
+            ImageExportFormat::WebP,
+            &name,
+            &image_options(&args),
+        )?,
+        ExportFormat::Thumbnail => {
+            let (max_width, max_height) = args.thumbnail_size;
+            let options = ThumbnailOptions {
+                max_width,
+                max_height,
+                format: RasterFormat::Jpeg {
+                    quality: quality(&args),
+                },
+            };
+            export_thumbnail_image(&document, &name, &options)?
+        }
+        ExportFormat::Svg => export_image(
+            &document,
+            ImageExportFormat::Svg,
+            &name,
+            &image_options(&args),
+        )?,
+        ExportFormat::Text => export_text_file(&document, &name)?,
     }
 
     Ok(())
 }
 
+fn pdf_options(args: &CompileArgs) -> PdfExportOptions {
+    PdfExportOptions {
+        encryption: args
+            .owner_password
+            .as_ref()
+            .map(|owner_password| PdfEncryption {
+                user_password: args.user_password.clone().unwrap_or_default(),
+                owner_password: owner_password.clone(),
+                permissions: PdfPermissions {
+                    print: args.allow.contains(&PdfPermission::Print),
+                    copy: args.allow.contains(&PdfPermission::Copy),
+                    modify: args.allow.contains(&PdfPermission::Modify),
+                },
+            }),
+    }
+}
+
+/// Quality of JPEG images and thumbnails.
+fn quality(args: &CompileArgs) -> u8 {
+    args.quality.unwrap_or(85)
+}
+
+fn image_options(args: &CompileArgs) -> ImageExportOptions {
+    ImageExportOptions {
+        pages: (!args.pages.is_empty()).then(|| PageRanges::new(args.pages.clone())),
+        pixels_per_pt: args.ppi / 72.0,
+        ..ImageExportOptions::default()
+    }
+}
+
 pub fn build_inputs(args: &CompileArgs) -> anyhow::Result<TemplateInputs> {
     let mut inputs = TemplateInputs::new();
     if !args.development {
@@ -0,0 +1,139 @@
+use crate::compile::export::ExportFormat;
+use crate::compile::{image_options, pdf_options, quality, CompileArgs};
+use anyhow::{bail, Context};
+use log::{error, info, warn};
+use oicana::Template;
+use oicana_export::format;
+use oicana_export::raster::{RasterFormat, ThumbnailOptions};
+use oicana_files::native::NativeTemplate;
+use oicana_input::input::json::JsonInput;
+use oicana_input::TemplateInputs;
+use serde_json::{Map, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Compile the template once for every line of the NDJSON file at `batch`.
+///
+/// Every line is a JSON object with input keys and values. They are added to the shared `inputs`.
+/// Each document is written to the output directory, named after its line number.
+/// The file is read line by line while compiling, so only the current chunk of input sets is kept
+/// in memory. Lines that are not a JSON object fail like documents that don't compile.
+pub fn compile_batch(
+    args: &CompileArgs,
+    template: &Template<NativeTemplate>,
+    inputs: TemplateInputs,
+    batch: &Path,
+) -> anyhow::Result<()> {
+    let open = || {
+        File::open(batch)
+            .map(BufReader::new)
+            .context("Failed to read batch file")
+    };
+    // Number the files with a fixed width, so they sort by line number.
+    let line_count = open()?.lines().count();
+    let width = 1 + line_count.checked_ilog10().unwrap_or(0) as usize;
+
+    let output_dir = args.output_dir.as_deref().unwrap_or(Path::new("output"));
+    fs::create_dir_all(output_dir).context("Failed to create the output directory")?;
+    info!(
+        "Compiling {line_count} lines of '{}' into '{}'.",
+        batch.display(),
+        output_dir.display()
+    );
+
+    let export_format = export_format(args);
+
+    // Line numbers of the input sets that are compiled, but not written yet
+    let pending_lines = RefCell::new(VecDeque::new());
+    let documents = Cell::new(0);
+    let failed = Cell::new(0);
+    let mut read_error = None;
+    let input_sets = open()?
+        .lines()
+        .map_while(|line| line.map_err(|error| read_error = Some(error)).ok())
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .filter_map(|(index, line)| {
+            let line_number = index + 1;
+            documents.set(documents.get() + 1);
+            let values: Map<String, Value> = match serde_json::from_str(&line) {
+                Ok(values) => values,
+                Err(error) => {
+                    error!("Line {line_number} is not a JSON object: {error}");
+                    failed.set(failed.get() + 1);
+                    return None;
+                }
+            };
+            let mut line_inputs = inputs.clone();
+            for (key, value) in values {
+                line_inputs.with_input(JsonInput::new(key, value.to_string()));
+            }
+            pending_lines.borrow_mut().push_back(line_number);
+            Some(line_inputs)
+        });
+
+    template.compile_and_export_batch(input_sets, &[export_format], |_, result| {
+        let line = pending_lines
+            .borrow_mut()
+            .pop_front()
+            .expect("Every result belongs to a line of the batch file");
+        match result {
+            Ok(exported) => {
+                if let Some(warnings) = exported.warnings {
+                    warn!("Warnings for line {line}:\n{warnings}");
+                }
+                let path = output_dir
+                    .join(format!("{line:0width$}"))
+                    .with_extension(args.format.extension());
+                // There is exactly one output, because there is one export format.
+                if let Err(error) = fs::write(&path, exported.outputs.concat()) {
+                    error!("Failed to write '{}': {error}", path.display());
+                    failed.set(failed.get() + 1);
+                }
+            }
+            Err(compilation_error) => {
+                error!("Failed to compile line {line}:\n{compilation_error}");
+                failed.set(failed.get() + 1);
+            }
+        }
+    });
+
+    if let Some(error) = read_error {
+        return Err(error).context("Failed to read batch file");
+    }
+    if failed.get() > 0 {
+        bail!("{} of {} documents failed", failed.get(), documents.get());
+    }
+
+    Ok(())
+}
+
+/// Multi-page documents are exported into a single file per line.
+fn export_format(args: &CompileArgs) -> format::ExportFormat {
+    match args.format {
+        ExportFormat::Pdf => format::ExportFormat::Pdf(pdf_options(args)),
+        ExportFormat::Png => format::ExportFormat::Png(image_options(args)),
+        ExportFormat::Jpeg => format::ExportFormat::Raster(
+            image_options(args),
+            RasterFormat::Jpeg {
+                quality: quality(args),
+            },
+        ),
+        ExportFormat::Webp => format::ExportFormat::Raster(image_options(args), RasterFormat::WebP),
+        ExportFormat::Svg => format::ExportFormat::Svg(image_options(args)),
+        ExportFormat::Text => format::ExportFormat::Text,
+        ExportFormat::Thumbnail => {
+            let (max_width, max_height) = args.thumbnail_size;
+            format::ExportFormat::Thumbnail(ThumbnailOptions {
+                max_width,
+                max_height,
+                format: RasterFormat::Jpeg {
+                    quality: quality(args),
+                },
+            })
+        }
+    }
+}
@@ -2,17 +2,26 @@ use anyhow::{bail, Context};
 use chrono::Utc;
 use clap::ValueEnum;
 use oicana::Template;
-use oicana_export::pdf::export_merged_pdf;
+use oicana_export::pages::ImageExportOptions;
+use oicana_export::pdf::{export_split_pdf, write_merged_pdf, PdfExportOptions};
+use oicana_export::png::write_png_page;
+use oicana_export::raster::{export_thumbnail, write_raster_page, RasterFormat, ThumbnailOptions};
+use oicana_export::svg::write_svg_page;
+use oicana_export::text::write_text;
 use oicana_files::native::NativeTemplate;
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
-use std::fs;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::num::NonZeroUsize;
 use std::path::Path;
-use typst::layout::PagedDocument;
+use typst::layout::{Page, PageRange, PagedDocument};
 
 pub fn export_pdf(
     document: &PagedDocument,
     template: &str,
     world: &Template<NativeTemplate>,
+    options: &PdfExportOptions,
 ) -> anyhow::Result<()> {
     let output = Path::new(".").to_path_buf().join("output").join(format!(
         "{}_{}.pdf",
@@ -20,39 +29,125 @@ pub fn export_pdf(
         Utc::now().timestamp_millis()
     ));
 
-    let pdf_buffer = match export_merged_pdf(document, world) {
-        Ok(pdf_buffer) => pdf_buffer,
+    fs::create_dir_all(Path::new(".").to_path_buf().join("output"))
+        .context("Failed to create the an output directory")?;
+    write_file(&output, |writer| {
+        if let Err(diagnostics) = write_merged_pdf(document, world, options, writer) {
+            bail!("Failed to compile PDF\n{diagnostics}");
+        }
+
+        Ok(())
+    })
+}
+
+/// Export one PDF file per part of the document, split at elements with the given label.
+pub fn export_split_pdf_files(
+    document: &PagedDocument,
+    world: &Template<NativeTemplate>,
+    label: &str,
+    options: &PdfExportOptions,
+    output_dir: &Path,
+) -> anyhow::Result<()> {
+    let parts = match export_split_pdf(document, world, label, options) {
+        Ok(parts) => parts,
         Err(diagnostics) => {
             bail!("Failed to compile PDF\n{diagnostics}");
         }
     };
 
-    fs::create_dir_all(Path::new(".").to_path_buf().join("output"))
-        .context("Failed to create the an output directory")?;
-    fs::write(output, pdf_buffer).context("Failed to write PDF")?;
+    // Check all names first, so that an invalid name doesn't leave a partial export behind.
+    let names = split_pdf_file_names(parts.iter().map(|part| part.name.as_str()))?;
+    fs::create_dir_all(output_dir).context("Failed to create the output directory")?;
+    for (part, name) in parts.into_iter().zip(names) {
+        let path = output_dir.join(format!("{name}.pdf"));
+        fs::write(&path, part.pdf)
+            .with_context(|| format!("Failed to write '{}'", path.display()))?;
+    }
 
     Ok(())
 }
 
+/// File names for the parts of a split PDF.
+///
+/// Names come from the template and must not escape the output directory.
+fn split_pdf_file_names<'a>(
+    names: impl IntoIterator<Item = &'a str>,
+) -> anyhow::Result<Vec<String>> {
+    let mut unique = HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let file_name: String = name
+                .chars()
+                .map(|char| match char {
+                    '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+                    char if char.is_control() => '_',
+                    char => char,
+                })
+                .collect();
+            if file_name.is_empty()
+                || file_name.starts_with('.')
+                || !unique.insert(file_name.clone())
+            {
+                bail!("Invalid or duplicate name '{name}' for a split PDF");
+            }
+
+            Ok(file_name)
+        })
+        .collect()
+}
+
 /// A format to export in.
 #[derive(Debug, Clone, ValueEnum)]
 pub enum ExportFormat {
     Pdf,
     Png,
+    Jpeg,
+    Webp,
     Svg,
+    /// A JPEG preview of the first page.
+    Thumbnail,
+    /// The plain text of all pages, separated by form feeds.
+    Text,
+}
+
+impl ExportFormat {
+    /// File extension of exported files.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg | ExportFormat::Thumbnail => "jpg",
+            ExportFormat::Webp => "webp",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Text => "txt",
+        }
+    }
+}
+
+/// Permissions that can be granted for encrypted PDFs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PdfPermission {
+    Print,
+    Copy,
+    Modify,
+    /// Grant no permissions. Can't be combined with other permissions.
+    None,
 }
 
 pub enum ImageExportFormat {
     Png,
+    Jpeg { quality: u8 },
+    WebP,
     Svg,
 }
 
-/// Export to one or multiple png files.
-// Todo: move single page exports to oicana_export
+/// Export the selected pages to one image file per page.
 pub fn export_image(
     document: &PagedDocument,
     fmt: ImageExportFormat,
     template: &str,
+    options: &ImageExportOptions,
 ) -> anyhow::Result<()> {
     let output = Path::new(".").to_path_buf().join("output").join(format!(
         "{template}_{}_{{n}}",
@@ -65,27 +160,37 @@ pub fn export_image(
     // 999 pages.
     let width = 1 + document.pages.len().checked_ilog10().unwrap_or(0) as usize;
 
-    document
-        .pages
+    options
+        .selected_pages(document)?
         .par_iter()
-        .enumerate()
-        .map(|(i, page)| {
-            let storage = string.replace("{n}", &format!("{:0width$}", i + 1));
+        .map(|(number, page)| {
+            let storage = string.replace("{n}", &format!("{number:0width$}"));
             let path = Path::new(&storage);
 
             match fmt {
-                ImageExportFormat::Png => {
-                    let pixmap = typst_render::render(page, 144.0 / 72.0);
-                    if let Err(encoding_error) = pixmap.save_png(path.with_extension("png")) {
+                ImageExportFormat::Png => write_file(&path.with_extension("png"), |writer| {
+                    if let Err(encoding_error) = write_png_page(page, options.pixels_per_pt, writer)
+                    {
                         bail!("Failed to encode image {encoding_error:?}");
                     }
+
+                    Ok(())
+                })?,
+                ImageExportFormat::Jpeg { quality } => {
+                    let format = RasterFormat::Jpeg { quality };
+                    write_raster(page, options, format, &path.with_extension("jpg"))?;
                 }
-                ImageExportFormat::Svg => {
-                    let svg = typst_svg::svg(page);
-                    if let Err(io_error) = fs::write(path.with_extension("svg"), svg.as_bytes()) {
-                        bail!("Failed to encode image {io_error:?}");
-                    }
+                ImageExportFormat::WebP => {
+                    write_raster(
+                        page,
+                        options,
+                        RasterFormat::WebP,
+                        &path.with_extension("webp"),
+                    )?;
                 }
+                ImageExportFormat::Svg => write_file(&path.with_extension("svg"), |writer| {
+                    write_svg_page(page, writer).context("Failed to write image")
+                })?,
             }
 
             Ok(())
@@ -94,3 +199,126 @@ pub fn export_image(
 
     Ok(())
 }
+
+fn write_raster(
+    page: &Page,
+    options: &ImageExportOptions,
+    format: RasterFormat,
+    path: &Path,
+) -> anyhow::Result<()> {
+    write_file(path, |writer| {
+        write_raster_page(page, options.pixels_per_pt, format, writer)
+            .context("Failed to encode image")
+    })
+}
+
+/// Export a thumbnail of the first page.
+pub fn export_thumbnail_image(
+    document: &PagedDocument,
+    template: &str,
+    options: &ThumbnailOptions,
+) -> anyhow::Result<()> {
+    let output = Path::new(".").to_path_buf().join("output").join(format!(
+        "{template}_{}_thumbnail.jpg",
+        Utc::now().timestamp_millis()
+    ));
+
+    let thumbnail = export_thumbnail(document, options).context("Failed to create thumbnail")?;
+
+    fs::create_dir_all(Path::new(".").to_path_buf().join("output"))
+        .context("Failed to create the an output directory")?;
+    fs::write(output, thumbnail).context("Failed to write thumbnail")?;
+
+    Ok(())
+}
+
+/// Export the plain text of the document.
+pub fn export_text_file(document: &PagedDocument, template: &str) -> anyhow::Result<()> {
+    let output = Path::new(".")
+        .to_path_buf()
+        .join("output")
+        .join(format!("{template}_{}.txt", Utc::now().timestamp_millis()));
+
+    fs::create_dir_all(Path::new(".").to_path_buf().join("output"))
+        .context("Failed to create the an output directory")?;
+    write_file(&output, |writer| {
+        write_text(document, writer).context("Failed to write text")
+    })
+}
+
+/// Stream an export into a new file at the given path.
+///
+/// The file is removed again if the export fails.
+fn write_file(
+    path: &Path,
+    export: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create '{}'", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let result = export(&mut writer).and_then(|()| {
+        writer
+            .flush()
+            .with_context(|| format!("Failed to write '{}'", path.display()))
+    });
+    if result.is_err() {
+        drop(writer);
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+/// Parse a bounding box like `256x256`.
+pub fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("'{value}' is not a valid size like 256x256");
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+
+    Ok((
+        width.trim().parse().map_err(|_| error())?,
+        height.trim().parse().map_err(|_| error())?,
+    ))
+}
+
+/// Parse a one-indexed page range like `3`, `1-3`, `2-` or `-4`.
+pub fn parse_page_range(value: &str) -> Result<PageRange, String> {
+    let parse_page = |page: &str| -> Result<Option<NonZeroUsize>, String> {
+        if page.is_empty() {
+            return Ok(None);
+        }
+        page.trim()
+            .parse::<NonZeroUsize>()
+            .map(Some)
+            .map_err(|_| format!("'{page}' is not a valid page number"))
+    };
+
+    match value.split_once('-') {
+        Some((first, last)) => Ok(parse_page(first)?..=parse_page(last)?),
+        None => {
+            let page = parse_page(value)?;
+            if page.is_none() {
+                return Err("page range must not be empty".to_owned());
+            }
+            Ok(page..=page)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_pdf_file_names;
+
+    #[test]
+    fn sanitizes_split_pdf_file_names() {
+        let names = split_pdf_file_names(["Invoice 1", "etc/passwd", "a:b"]).unwrap();
+
+        assert_eq!(names, ["Invoice 1", "etc_passwd", "a_b"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_split_pdf_file_names() {
+        assert!(split_pdf_file_names(["a/b", "a_b"]).is_err());
+        assert!(split_pdf_file_names(["../secret"]).is_err());
+        assert!(split_pdf_file_names([""]).is_err());
+    }
+}
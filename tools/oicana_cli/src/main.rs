@@ -4,6 +4,7 @@
 
 mod compile;
 mod pack;
+mod registry;
 mod target;
 mod test;
 mod validate;
@@ -28,7 +29,7 @@ fn main() -> Result<(), Error> {
     trace!("{cli:?}");
 
     match cli.command {
-        Oicana::Compile(args) => compile(args)?,
+        Oicana::Compile(args) => compile(*args)?,
         Oicana::Validate(validate_args) => validate(validate_args)?,
         Oicana::Pack(package_args) => pack(package_args)?,
         Oicana::Test(test_args) => test(test_args)?,
@@ -52,7 +53,7 @@ struct Cli {
 #[derive(Parser, Debug)]
 enum Oicana {
     #[clap(about = "Compile oicana templates", after_help = COMPILE_AFTER_HELP)]
-    Compile(CompileArgs),
+    Compile(Box<CompileArgs>),
     #[clap(about = "Validate oicana templates", after_help = VALIDATE_AFTER_HELP)]
     Validate(ValidateArgs),
     #[clap(about = "Package oicana templates", after_help = PACK_AFTER_HELP)]
@@ -1,18 +1,22 @@
+use crate::registry::RegistryArgs;
 use crate::target::TargetArgs;
 use anyhow::Context;
-use clap::Args;
-use log::info;
+use clap::{Args, ValueEnum};
+use log::{debug, info, warn};
+use oicana_files::lock::{hash_package_dir, LockError, LockedPackage, TemplateLock, LOCKFILE};
 use oicana_files::native::{package_data_dir, NativeTemplate};
+use oicana_files::signature::TemplateSigner;
 use oicana_files::TemplateFiles;
-use oicana_template::package::package;
+use oicana_template::manifest::TemplateManifest;
+use oicana_template::package::{package, packed_entries, Compression};
+use sha2::{Digest, Sha256};
 use std::collections::HashSet;
 use std::ffi::OsStr;
-use std::fs::{create_dir_all, read_dir, remove_dir_all, File};
-use std::path::Path;
+use std::fs::{create_dir_all, read, read_dir, read_to_string, remove_dir_all, write, File};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use typst::syntax::ast::ModuleImport;
 use typst::syntax::package::PackageSpec;
-use typst::syntax::{ast, FileId, VirtualPath};
+use typst::syntax::{ast, FileId, Source, SyntaxNode, VirtualPath};
 
 #[derive(Debug, Args)]
 pub struct PackArgs {
@@ -20,6 +24,58 @@ pub struct PackArgs {
     target: TargetArgs,
     #[clap(short, long, help = "Output directory", default_value = ".")]
     output: String,
+    #[arg(
+        long,
+        help = "Compression of the files in the archive",
+        default_value = "zstd"
+    )]
+    compression: PackCompression,
+    #[clap(flatten)]
+    registry: RegistryArgs,
+    #[arg(
+        long,
+        help = "Fail if the resolved dependencies differ from the lockfile instead of updating it"
+    )]
+    locked: bool,
+    #[arg(
+        long,
+        help = "List the files that would be packed without resolving dependencies or writing the archive or the lockfile",
+        conflicts_with = "locked"
+    )]
+    dry_run: bool,
+    #[arg(
+        long,
+        help = "Print the SHA-256 hash of each archive. Archives of the same files have the same hash.",
+        conflicts_with = "dry_run"
+    )]
+    hash: bool,
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Sign the archive with the Ed25519 private key in the given PEM file",
+        conflicts_with = "dry_run"
+    )]
+    sign: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PackCompression {
+    /// Supported by all zip tools.
+    Deflate,
+    /// Smaller archives, but not supported by all zip tools.
+    Zstd,
+    /// No compression.
+    Stored,
+}
+
+impl From<PackCompression> for Compression {
+    fn from(compression: PackCompression) -> Self {
+        match compression {
+            PackCompression::Deflate => Compression::Deflate,
+            PackCompression::Zstd => Compression::Zstd,
+            PackCompression::Stored => Compression::Stored,
+        }
+    }
 }
 
 #[rustfmt::skip]
@@ -29,31 +85,149 @@ pub const PACK_AFTER_HELP: &str = color_print::cstr!("\
   oicana pack templates/invoice -o out
   oicana pack -a
   oicana pack templates -a
+  oicana pack templates/invoice --dry-run
+  oicana pack templates/invoice --compression deflate
+  oicana pack templates/invoice --sign key.pem
+  SOURCE_DATE_EPOCH=1700000000 oicana pack templates/invoice --hash
 ");
 
 pub fn pack(args: PackArgs) -> anyhow::Result<()> {
-    // Todo: read `exclude` from manifest and default to exclude zip files and the output dir to better support single dir templates
     let templates = args.target.get_targets()?;
     let out = Path::new(&args.output);
     let packages = package_data_dir().context("Failed to find data directory for packages")?;
+    let signer = match &args.sign {
+        Some(key) => {
+            let pem = read_to_string(key)
+                .with_context(|| format!("Failed to read the signing key {}", key.display()))?;
+            Some(TemplateSigner::from_pem(&pem)?)
+        }
+        None => None,
+    };
 
     for template in templates {
-        info!("Packing template '{}'.", template.manifest.package.name);
         template.manifest.validate()?;
+        if args.dry_run {
+            list_packed_files(&template.path, &template.manifest)?;
+            continue;
+        }
+        info!("Packing template '{}'.", template.manifest.package.name);
 
-        let mut files = NativeTemplate::new(&template.path, packages.clone());
+        let files = NativeTemplate::new(&template.path, packages.clone(), args.registry.registry());
 
         let out_file_path = out.join(format!(
             "{}-{}.zip",
             template.manifest.package.name, template.manifest.package.version
         ));
 
-        update_dependencies(&template.path, &mut files)?;
+        let dependencies = update_dependencies(&template.path, &files)?;
+        lock_dependencies(&template.path, &dependencies, args.locked)?;
 
         create_dir_all(out)?;
-        let mut out_file = File::create(out_file_path).context("Failed to create the zip file")?;
+        let mut out_file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&out_file_path)
+            .context("Failed to create the zip file")?;
 
-        package(&template.path, &mut out_file, &template.manifest)?;
+        package(
+            &template.path,
+            &mut out_file,
+            &template.manifest,
+            args.compression.into(),
+        )?;
+        if let Some(signer) = &signer {
+            signer
+                .sign_archive(&mut out_file)
+                .context("Failed to sign the zip file")?;
+        }
+        if args.hash {
+            let archive = read(&out_file_path).context("Failed to read the zip file")?;
+            println!("{:x}  {}", Sha256::digest(archive), out_file_path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print the files that would be packed, relative to the template root, and the imported packages.
+///
+/// Dependencies are not resolved, so the template directory is left untouched and no packages
+/// are downloaded. Packages imported by other packages are only known after resolving them.
+fn list_packed_files(root: &Path, manifest: &TemplateManifest) -> anyhow::Result<()> {
+    let mut files: Vec<_> = packed_entries(root, manifest)?
+        .into_iter()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(root).ok().map(Path::to_path_buf))
+        .filter(|path| !path.starts_with(".dependencies"))
+        .collect();
+    files.sort();
+
+    let mut imports = vec![];
+    for file in files
+        .iter()
+        .filter(|file| file.extension().and_then(OsStr::to_str) == Some("typ"))
+    {
+        let text = read_to_string(root.join(file))
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let source = Source::new(FileId::new(None, VirtualPath::new(file)), text);
+        for spec in package_imports(&source) {
+            if !imports.contains(&spec) {
+                imports.push(spec);
+            }
+        }
+    }
+
+    println!(
+        "{} files of template '{}' would be packed:",
+        files.len(),
+        manifest.package.name
+    );
+    for file in files {
+        println!("  {}", file.display());
+    }
+    if !imports.is_empty() {
+        println!("Imported packages would be packed with their dependencies:");
+        for spec in imports {
+            println!("  {spec}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the resolved dependencies into the lockfile of the template.
+///
+/// If `locked` is set, the lockfile is not changed. Instead, resolved dependencies that differ
+/// from the lockfile are an error.
+fn lock_dependencies(
+    root: &Path,
+    dependencies: &HashSet<PackageSpec>,
+    locked: bool,
+) -> anyhow::Result<()> {
+    let mut packages = vec![];
+    for spec in dependencies {
+        let package_dir = root
+            .join(".dependencies")
+            .join(spec.namespace.as_str())
+            .join(spec.name.as_str())
+            .join(spec.version.to_string());
+        let hash = hash_package_dir(&package_dir)
+            .with_context(|| format!("Failed to hash the files of {spec}"))?;
+        packages.push(LockedPackage {
+            spec: spec.clone(),
+            hash,
+        });
+    }
+    let lock = TemplateLock::new(packages);
+
+    let lockfile = root.join(LOCKFILE);
+    if locked {
+        let existing = read_to_string(&lockfile).map_err(|_| LockError::NoLockfile)?;
+        TemplateLock::from_toml(&existing)?.verify(&lock)?;
+    } else {
+        write(&lockfile, lock.to_toml()).context("Failed to write the lockfile")?;
     }
 
     Ok(())
@@ -61,15 +235,20 @@ pub fn pack(args: PackArgs) -> anyhow::Result<()> {
 
 /// This will remove the current `.dependencies` directory and
 /// resolve all imports from the template, recreating `.dependencies`
-/// along the way.
-fn update_dependencies<Files: TemplateFiles>(root: &Path, files: &mut Files) -> anyhow::Result<()> {
+/// along the way. Packages are found by the package resolver of the template.
+///
+/// Returns all resolved packages.
+fn update_dependencies(
+    root: &Path,
+    files: &NativeTemplate,
+) -> anyhow::Result<HashSet<PackageSpec>> {
     let _ignore = remove_dir_all(root.join(".dependencies"));
     let mut imported_dependencies = HashSet::new();
 
-    fn prepare_dependencies<Files: TemplateFiles>(
+    fn prepare_dependencies(
         root: &Path,
         dir: &Path,
-        files: &mut Files,
+        files: &NativeTemplate,
         imported_dependencies: &mut HashSet<PackageSpec>,
     ) -> anyhow::Result<()> {
         if dir.file_name().and_then(OsStr::to_str) == Some(".dependencies") {
@@ -92,35 +271,25 @@ fn update_dependencies<Files: TemplateFiles>(root: &Path, files: &mut Files) ->
                     )?),
                 );
                 let source = files.source(fid).context("Can't read source file")?;
-                let imports = source
-                    .root()
-                    .children()
-                    .filter_map(|ch| ch.cast::<ModuleImport>());
-                for import in imports {
-                    let ast::Expr::Str(source_str) = import.source() else {
-                        continue;
-                    };
-
-                    if let Ok(import_spec) = PackageSpec::from_str(source_str.get().as_str()) {
-                        let package_file_id =
-                            FileId::new(Some(import_spec.clone()), VirtualPath::new("/typst.toml"));
-                        // Request the package manifest file. This will be cached in case we already prepared the dependency.
-                        // Otherwise the package will be copied from the local machine's Typst cache or downloaded from the registry.
-                        files.file(package_file_id).context(format!(
-                            "Failed to prepare package for file {package_file_id:?}"
-                        ))?;
-                        if imported_dependencies.insert(import_spec.clone()) {
-                            prepare_dependencies(
-                                root,
-                                &root
-                                    .join(".dependencies")
-                                    .join(import_spec.namespace.to_string())
-                                    .join(import_spec.name.to_string())
-                                    .join(import_spec.version.to_string()),
-                                files,
-                                imported_dependencies,
-                            )?;
-                        }
+                for import_spec in package_imports(&source) {
+                    let package_file_id =
+                        FileId::new(Some(import_spec.clone()), VirtualPath::new("/typst.toml"));
+                    // Request the package manifest file. This will be cached in case we already prepared the dependency.
+                    // Otherwise the package will be copied from the local machine's Typst cache or downloaded from the registry.
+                    files.file(package_file_id).context(format!(
+                        "Failed to prepare package for file {package_file_id:?}"
+                    ))?;
+                    if imported_dependencies.insert(import_spec.clone()) {
+                        prepare_dependencies(
+                            root,
+                            &root
+                                .join(".dependencies")
+                                .join(import_spec.namespace.to_string())
+                                .join(import_spec.name.to_string())
+                                .join(import_spec.version.to_string()),
+                            files,
+                            imported_dependencies,
+                        )?;
                     }
                 }
             }
@@ -129,63 +298,114 @@ fn update_dependencies<Files: TemplateFiles>(root: &Path, files: &mut Files) ->
         Ok(())
     }
 
-    prepare_dependencies(root, root, files, &mut imported_dependencies)
+    prepare_dependencies(root, root, files, &mut imported_dependencies)?;
+
+    Ok(imported_dependencies)
+}
+
+/// Find the packages of all imports and includes anywhere in the source file.
+///
+/// Paths that are computed at runtime can't be resolved while packing. They are skipped with a
+/// warning.
+fn package_imports(source: &Source) -> Vec<PackageSpec> {
+    fn visit(source: &Source, node: &SyntaxNode, specs: &mut Vec<PackageSpec>) {
+        let import_source = match node.cast::<ast::ModuleImport>() {
+            Some(import) => Some(import.source()),
+            None => node
+                .cast::<ast::ModuleInclude>()
+                .map(|include| include.source()),
+        };
+        match import_source {
+            Some(ast::Expr::Str(path)) => {
+                if let Ok(spec) = PackageSpec::from_str(path.get().as_str()) {
+                    specs.push(spec);
+                }
+            }
+            // Imports from a module in a variable, e.g. `import utils: table`
+            Some(ast::Expr::Ident(_) | ast::Expr::FieldAccess(_)) => {
+                debug!(
+                    "Skipping import of a module expression in {:?}",
+                    source.id().vpath()
+                );
+            }
+            Some(_) => {
+                let line = source
+                    .range(node.span())
+                    .and_then(|range| source.byte_to_line(range.start))
+                    .map_or(0, |line| line + 1);
+                warn!(
+                    "The path of the import in {}:{line} is computed at runtime and can't be resolved while packing. \
+                    Packages imported this way are missing in the packed template.",
+                    source.id().vpath().as_rootless_path().display()
+                );
+            }
+            None => {}
+        }
+
+        for child in node.children() {
+            visit(source, child, specs);
+        }
+    }
+
+    let mut specs = vec![];
+    visit(source, source.root(), &mut specs);
+
+    specs
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use oicana_files::{native::NativeTemplate, TemplateFiles};
+    use clap::Parser;
+    use oicana_files::native::{NativeTemplate, PackageResolver};
     use std::{
         collections::{HashMap, HashSet},
-        fs::create_dir_all,
+        fs::{create_dir_all, write},
         io::Write,
         path::PathBuf,
-        sync::RwLock,
+        sync::{Arc, RwLock},
     };
     use tempfile::tempdir;
-    use typst::{foundations::Bytes, syntax::package::PackageSpec};
+    use typst::{diag::PackageResult, syntax::package::PackageSpec};
 
-    struct TestFiles {
-        native: NativeTemplate,
+    // We prevent network calls or checks in the local Typst cache here.
+    struct TestResolver {
+        directory: PathBuf,
         mocked_packages: HashMap<PackageSpec, String>,
         packages: RwLock<HashSet<PackageSpec>>,
-        root: PathBuf,
     }
 
-    impl TemplateFiles for TestFiles {
-        fn source(
-            &self,
-            id: typst::syntax::FileId,
-        ) -> typst::diag::FileResult<typst::syntax::Source> {
-            self.native.source(id)
+    impl TestResolver {
+        fn new(directory: PathBuf, mocked_packages: HashMap<PackageSpec, String>) -> Arc<Self> {
+            Arc::new(TestResolver {
+                directory,
+                mocked_packages,
+                packages: RwLock::new(HashSet::new()),
+            })
         }
+    }
 
-        // We prevent network calls or checks in the local Typst cache here.
-        fn file(
-            &self,
-            id: typst::syntax::FileId,
-        ) -> typst::diag::FileResult<typst::foundations::Bytes> {
-            if let Some(spec) = id.package() {
-                self.packages.write().unwrap().insert(spec.clone());
-                let package = self.mocked_packages.get(spec).unwrap();
-                let subdir = format!(
-                    ".dependencies/{}/{}/{}",
-                    spec.namespace, spec.name, spec.version
-                );
-                create_dir_all(self.root.join(&subdir)).unwrap();
-                let mut tmp_file =
-                    File::create(self.root.join(&subdir).join("package.typ")).unwrap();
-                tmp_file.write_all(package.as_bytes()).unwrap();
-
-                return Ok(Bytes::from_string(package.clone()));
-            }
-
-            self.native.file(id)
-        }
+    impl PackageResolver for TestResolver {
+        fn resolve(&self, spec: &PackageSpec) -> PackageResult<Option<PathBuf>> {
+            self.packages.write().unwrap().insert(spec.clone());
+            let Some(package) = self.mocked_packages.get(spec) else {
+                return Ok(None);
+            };
+            let dir = self
+                .directory
+                .join(format!("{}/{}/{}", spec.namespace, spec.name, spec.version));
+            create_dir_all(&dir).unwrap();
+            write(
+                dir.join("typst.toml"),
+                format!(
+                    "[package]\nname = \"{}\"\nversion = \"{}\"\nentrypoint = \"package.typ\"",
+                    spec.name, spec.version
+                ),
+            )
+            .unwrap();
+            write(dir.join("package.typ"), package).unwrap();
 
-        fn font_files(&self) -> &Vec<typst::syntax::FileId> {
-            todo!()
+            Ok(Some(dir))
         }
     }
 
@@ -202,15 +422,11 @@ mod tests {
                 .write_all("This Typst file has no imports!".as_bytes())
                 .unwrap();
         }
-        let mut files = TestFiles {
-            native: NativeTemplate::new(&temp_template, temp_packages),
-            mocked_packages: HashMap::new(),
-            packages: RwLock::new(HashSet::new()),
-            root: temp_template.to_path_buf(),
-        };
+        let resolver = TestResolver::new(temp_packages, HashMap::new());
+        let files = NativeTemplate::with_resolver(&temp_template, resolver.clone());
 
-        update_dependencies(&temp_template, &mut files).unwrap();
-        assert!(files.packages.read().unwrap().is_empty());
+        update_dependencies(&temp_template, &files).unwrap();
+        assert!(resolver.packages.read().unwrap().is_empty());
         assert_eq!(
             temp_template.join(".dependencies").try_exists().ok(),
             Some(false)
@@ -233,19 +449,14 @@ mod tests {
                 )
                 .unwrap();
         }
-        let root = temp_template.to_path_buf();
         let spec = PackageSpec::from_str("@preview/test:0.1.0").unwrap();
         let mut mocked_packages = HashMap::new();
         mocked_packages.insert(spec.clone(), "Some package content".to_owned());
-        let mut files = TestFiles {
-            native: NativeTemplate::new(&temp_template, temp_packages),
-            mocked_packages,
-            packages: RwLock::new(HashSet::new()),
-            root: root.clone(),
-        };
-        update_dependencies(&temp_template, &mut files).unwrap();
-        assert_eq!(files.packages.read().unwrap().len(), 1);
-        assert!(files.packages.read().unwrap().get(&spec).is_some());
+        let resolver = TestResolver::new(temp_packages, mocked_packages);
+        let files = NativeTemplate::with_resolver(&temp_template, resolver.clone());
+        update_dependencies(&temp_template, &files).unwrap();
+        assert_eq!(resolver.packages.read().unwrap().len(), 1);
+        assert!(resolver.packages.read().unwrap().get(&spec).is_some());
         assert_eq!(
             temp_template.join(".dependencies").try_exists().ok(),
             Some(true)
@@ -268,7 +479,6 @@ mod tests {
                 )
                 .unwrap();
         }
-        let root = temp_template.to_path_buf();
         let spec = PackageSpec::from_str("@preview/test:0.1.0").unwrap();
         let spec2 = PackageSpec::from_str("@preview/test2:0.1.0").unwrap();
         let mut mocked_packages = HashMap::new();
@@ -277,19 +487,138 @@ mod tests {
             "#import \"@preview/test2:0.1.0\": *\nSome package content with import".to_owned(),
         );
         mocked_packages.insert(spec2.clone(), "Some other package content".to_owned());
-        let mut files = TestFiles {
-            native: NativeTemplate::new(&temp_template, temp_packages),
-            mocked_packages,
-            packages: RwLock::new(HashSet::new()),
-            root: root.clone(),
-        };
-        update_dependencies(&temp_template, &mut files).unwrap();
-        assert_eq!(files.packages.read().unwrap().len(), 2);
-        assert!(files.packages.read().unwrap().get(&spec).is_some());
-        assert!(files.packages.read().unwrap().get(&spec2).is_some());
+        let resolver = TestResolver::new(temp_packages, mocked_packages);
+        let files = NativeTemplate::with_resolver(&temp_template, resolver.clone());
+        update_dependencies(&temp_template, &files).unwrap();
+        assert_eq!(resolver.packages.read().unwrap().len(), 2);
+        assert!(resolver.packages.read().unwrap().get(&spec).is_some());
+        assert!(resolver.packages.read().unwrap().get(&spec2).is_some());
         assert_eq!(
             temp_template.join(".dependencies").try_exists().ok(),
             Some(true)
         );
     }
+
+    #[test]
+    fn locked_packing_fails_for_changed_dependencies() {
+        let tempdir = tempdir().unwrap();
+        let temp_template = tempdir.path().join("template");
+        create_dir_all(&temp_template).unwrap();
+        write(
+            temp_template.join("test.typ"),
+            "#import \"@preview/test:0.1.0\": *\nThis Typst file imports the test package.",
+        )
+        .unwrap();
+        let spec = PackageSpec::from_str("@preview/test:0.1.0").unwrap();
+        let mut mocked_packages = HashMap::new();
+        mocked_packages.insert(spec.clone(), "Some package content".to_owned());
+        let resolver = TestResolver::new(tempdir.path().join("cache"), mocked_packages);
+        let files = NativeTemplate::with_resolver(&temp_template, resolver);
+
+        assert!(lock_dependencies(&temp_template, &HashSet::new(), true).is_err());
+        let dependencies = update_dependencies(&temp_template, &files).unwrap();
+        lock_dependencies(&temp_template, &dependencies, false).unwrap();
+        let lock = TemplateLock::from_toml(&read_to_string(temp_template.join(LOCKFILE)).unwrap())
+            .unwrap();
+        assert_eq!(lock.packages.len(), 1);
+        assert_eq!(lock.packages[0].spec, spec);
+        lock_dependencies(&temp_template, &dependencies, true).unwrap();
+
+        write(
+            temp_template.join(".dependencies/preview/test/0.1.0/package.typ"),
+            "Changed package content",
+        )
+        .unwrap();
+        let error = lock_dependencies(&temp_template, &dependencies, true).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<LockError>(),
+            Some(LockError::Changed { .. })
+        ));
+    }
+
+    #[derive(Parser)]
+    struct PackCli {
+        #[command(flatten)]
+        args: PackArgs,
+    }
+
+    #[test]
+    fn dry_run_does_not_touch_dependencies() {
+        let tempdir = tempdir().unwrap();
+        let temp_template = tempdir.path().join("template");
+        create_dir_all(temp_template.join(".dependencies/preview/test/0.1.0")).unwrap();
+        write(
+            temp_template.join("typst.toml"),
+            r#"
+            [package]
+            name = "test"
+            version = "0.1.0"
+            entrypoint = "main.typ"
+
+            [tool.oicana]
+            manifest_version = 1
+            "#,
+        )
+        .unwrap();
+        write(
+            temp_template.join("main.typ"),
+            "#import \"@preview/test:0.1.0\": *\nThis Typst file imports the test package.",
+        )
+        .unwrap();
+        let package_file = temp_template.join(".dependencies/preview/test/0.1.0/package.typ");
+        write(&package_file, "Some package content").unwrap();
+
+        let cli = PackCli::parse_from([
+            "pack",
+            temp_template.to_str().unwrap(),
+            "--dry-run",
+            "--offline",
+            "--output",
+            tempdir.path().join("out").to_str().unwrap(),
+        ]);
+        pack(cli.args).unwrap();
+
+        assert_eq!(
+            read_to_string(&package_file).unwrap(),
+            "Some package content"
+        );
+        assert_eq!(
+            temp_template
+                .join(".dependencies/preview/test/0.1.0/typst.toml")
+                .try_exists()
+                .ok(),
+            Some(false)
+        );
+        assert_eq!(temp_template.join(LOCKFILE).try_exists().ok(), Some(false));
+        assert_eq!(tempdir.path().join("out").try_exists().ok(), Some(false));
+    }
+
+    #[test]
+    fn finds_nested_package_imports() {
+        let source = Source::detached(
+            r#"
+            #import "@preview/top:0.1.0": *
+            #{
+              import "@preview/block:0.1.0"
+            }
+            #let render() = {
+              import "@preview/function:0.1.0": render
+              render()
+            }
+            #if true [
+              #include "@preview/conditional:0.1.0"
+            ]
+            #import "local.typ" as local
+            #import local: helper
+            #import "@preview/" + "dynamic:0.1.0": *
+            "#,
+        );
+
+        let names: Vec<_> = package_imports(&source)
+            .into_iter()
+            .map(|spec| spec.name)
+            .collect();
+
+        assert_eq!(names, ["top", "block", "function", "conditional"]);
+    }
 }
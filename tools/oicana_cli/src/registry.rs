@@ -0,0 +1,28 @@
+use clap::Args;
+use oicana_files::native::PackageRegistry;
+
+#[derive(Debug, Args)]
+pub struct RegistryArgs {
+    #[arg(
+        long,
+        help = "Fail instead of downloading packages that are not in the local package directory"
+    )]
+    offline: bool,
+    #[arg(
+        long,
+        help = "Download packages from this mirror of the Typst package registry",
+        value_name = "URL",
+        conflicts_with = "offline"
+    )]
+    package_registry: Option<String>,
+}
+
+impl RegistryArgs {
+    pub fn registry(&self) -> PackageRegistry {
+        match self.package_registry {
+            _ if self.offline => PackageRegistry::Offline,
+            Some(ref url) => PackageRegistry::Mirror(url.clone()),
+            None => PackageRegistry::Default,
+        }
+    }
+}
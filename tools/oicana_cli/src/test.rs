@@ -1,5 +1,6 @@
 use std::time::Instant;
 
+use crate::registry::RegistryArgs;
 use crate::target::{TargetArgs, TemplateDir};
 use anyhow::bail;
 use clap::Args;
@@ -11,6 +12,8 @@ use oicana_testing::{collect::TemplateTests, execution::TestRunnerContext};
 pub struct TestArgs {
     #[clap(flatten)]
     target: TargetArgs,
+    #[clap(flatten)]
+    registry: RegistryArgs,
 }
 
 static LOOKING_GLASS: Emoji<'_, '_> = Emoji("🔍", "");
@@ -25,7 +28,7 @@ pub fn test(args: TestArgs) -> anyhow::Result<()> {
     let error = style("Error").red();
     let warning = style("Warning").yellow();
 
-    let test_runner_context = TestRunnerContext::new()?;
+    let test_runner_context = TestRunnerContext::new(args.registry.registry())?;
     let started = Instant::now();
 
     println!(